@@ -5,13 +5,17 @@ use std::collections::HashMap;
 use hyper::server::Request;
 use hyper::status::StatusCode;
 use hyper::method::Method;
+use hyper::uri::RequestUri;
+use url::form_urlencoded;
+use url::percent_encoding::percent_decode;
 use uuid::Uuid;
 
-use super::super::Result;
+use super::super::{Result, Error, ErrorKind};
 use super::super::constants::VERSION;
 use super::super::utils::Addr;
 use super::super::sdk::SdkInfo;
 use super::super::memdb::read::{MemDb, Symbol as MemDbSymbol};
+use super::super::dsym::SectionKind;
 use super::super::memdb::stash::MemDbStash;
 use super::server::{ServerContext, load_request_data};
 use super::types::{ApiResponse, ApiError};
@@ -29,6 +33,12 @@ struct Symbol {
     object_name: Option<String>,
     symbol: Option<String>,
     addr: Addr,
+    size: Option<u64>,
+    offset: Option<u64>,
+    arch: Option<String>,
+    image_addr: Option<Addr>,
+    image_size: Option<u64>,
+    section: Option<String>,
 }
 
 macro_rules! assert_method {
@@ -40,15 +50,72 @@ macro_rules! assert_method {
     }
 }
 
-impl<'a> From<MemDbSymbol<'a>> for Symbol {
-    fn from(sym: MemDbSymbol<'a>) -> Symbol {
+impl Symbol {
+    /// Converts a memdb symbol and adds the metadata of its image.
+    fn from_memdb(memdb: &MemDb, sym: MemDbSymbol) -> Result<Symbol> {
         Symbol {
             object_uuid: Some(sym.object_uuid()),
             object_name: Some(sym.object_name().to_string()),
             symbol: Some(sym.symbol().to_string()),
             addr: Addr(sym.addr()),
+            size: sym.size(),
+            offset: Some(sym.offset()),
+            arch: None,
+            image_addr: None,
+            image_size: None,
+            section: Some(sym.section_kind().name().to_string()),
+        }.with_object_info(memdb)
+    }
+
+    /// Describes an address inside a function that has no symbol.
+    fn unnamed_function(memdb: &MemDb, uuid: &Uuid, start: u64, size: Option<u64>,
+                        addr: u64) -> Result<Symbol> {
+        Symbol {
+            object_uuid: Some(*uuid),
+            object_name: None,
+            symbol: None,
+            addr: Addr(start),
+            size: size,
+            offset: Some(addr - start),
+            arch: None,
+            image_addr: None,
+            image_size: None,
+            section: Some(SectionKind::Text.name().to_string()),
+        }.with_object_info(memdb)
+    }
+
+    /// Adds the metadata of the image if the memdb stores it.
+    fn with_object_info(mut self, memdb: &MemDb) -> Result<Symbol> {
+        let uuid = match self.object_uuid {
+            Some(uuid) => uuid,
+            None => { return Ok(self); }
+        };
+        if let Some(info) = memdb.object_info(&uuid)? {
+            if self.object_name.is_none() {
+                self.object_name = Some(info.path().to_string());
+            }
+            self.arch = Some(info.arch().to_string());
+            self.image_addr = Some(Addr(info.vmaddr()));
+            if info.vmsize() > 0 {
+                self.image_size = Some(info.vmsize());
+            }
         }
+        Ok(self)
+    }
+}
+
+/// Looks up an address in an image.
+///
+/// Addresses in functions without symbol resolve to an entry without
+/// symbol name.
+fn lookup_symbol(memdb: &MemDb, uuid: &Uuid, addr: u64) -> Result<Option<Symbol>> {
+    if let Some(sym) = memdb.lookup_by_uuid(uuid, addr) {
+        return Ok(Some(Symbol::from_memdb(memdb, sym)?));
     }
+    if let Some((start, size)) = memdb.lookup_unnamed_function(uuid, addr) {
+        return Ok(Some(Symbol::unnamed_function(memdb, uuid, start, size, addr)?));
+    }
+    Ok(None)
 }
 
 #[derive(Serialize)]
@@ -56,6 +123,11 @@ struct SymbolResponse {
     symbols: Vec<Option<Symbol>>,
 }
 
+#[derive(Serialize)]
+struct FindSymbolResponse {
+    symbols: Vec<Symbol>,
+}
+
 #[derive(Serialize)]
 struct SdksResponse {
     sdks: Vec<String>,
@@ -119,18 +191,19 @@ pub fn lookup_symbol_handler(ctx: &ServerContext, mut req: Request) -> Result<Ap
         let mut rvsym = None;
         if let Some(ref uuid) = symq.object_uuid {
             for sdk_info in sdk_infos.iter() {
-                if let Some(sym) = lc.get_memdb(sdk_info)?.lookup_by_uuid(
-                   uuid, symq.addr.into()) {
-                    rvsym = Some(sym.into());
+                rvsym = lookup_symbol(&lc.get_memdb(sdk_info)?, uuid, symq.addr.into())?;
+                if rvsym.is_some() {
                     break;
                 }
             }
         } else if let Some(ref name) = symq.object_name {
             for sdk_info in sdk_infos.iter() {
-                if let Some(sym) = lc.get_memdb(sdk_info)?.lookup_by_object_name(
-                   name, &data.cpu_name, symq.addr.into()) {
-                    rvsym = Some(sym.into());
-                    break;
+                let memdb = lc.get_memdb(sdk_info)?;
+                if let Ok(Some(uuid)) = memdb.find_uuid(name, &data.cpu_name) {
+                    rvsym = lookup_symbol(&memdb, uuid, symq.addr.into())?;
+                    if rvsym.is_some() {
+                        break;
+                    }
                 }
             }
         }
@@ -142,6 +215,48 @@ pub fn lookup_symbol_handler(ctx: &ServerContext, mut req: Request) -> Result<Ap
     }, StatusCode::Ok)
 }
 
+/// Finds all addresses of a symbol in an SDK.
+///
+/// This handles `/sdks/<id>/symbols?name=<symbol>`.
+pub fn find_symbol_handler(ctx: &ServerContext, req: Request) -> Result<ApiResponse>
+{
+    assert_method!(req, Method::Get);
+    let (path, query) = match req.uri {
+        RequestUri::AbsolutePath(ref path) => {
+            let mut parts = path.splitn(2, '?');
+            (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+        }
+        _ => { return Err(ApiError::BadRequest.into()); }
+    };
+
+    if path.len() < "/sdks//symbols".len() {
+        return Err(ApiError::NotFound.into());
+    }
+    let sdk_id = percent_decode(path["/sdks/".len()..path.len() - "/symbols".len()]
+        .as_bytes()).decode_utf8().map_err(|_| Error::from(ApiError::BadRequest))?;
+    let name = match form_urlencoded::parse(query.as_bytes())
+        .find(|&(ref key, _)| key == "name") {
+        Some((_, value)) => value,
+        None => { return Err(ApiError::BadRequest.into()); }
+    };
+
+    let memdb = match ctx.stash.get_memdb_from_sdk_id(&sdk_id) {
+        Ok(memdb) => memdb,
+        Err(Error(ErrorKind::UnknownSdk, _)) => {
+            return Err(ApiError::SdkNotFound.into());
+        }
+        Err(err) => { return Err(err); }
+    };
+
+    let mut symbols = vec![];
+    for sym in memdb.find_symbol(&name)? {
+        symbols.push(Symbol::from_memdb(&memdb, sym)?);
+    }
+    ApiResponse::new(FindSymbolResponse {
+        symbols: symbols,
+    }, StatusCode::Ok)
+}
+
 /// Lists all found SDKs.
 pub fn list_sdks_handler(ctx: &ServerContext, req: Request) -> Result<ApiResponse>
 {
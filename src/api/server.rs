@@ -185,11 +185,15 @@ impl ApiServer {
             let is_head = req.method == Method::Head;
             let handler = match req.uri {
                 RequestUri::AbsolutePath(ref path) => {
-                    match path.as_str() {
+                    let path = path.split('?').next().unwrap_or("");
+                    match path {
                         "/health" => handlers::healthcheck_handler,
                         "/lookup" => handlers::lookup_symbol_handler,
                         "/sdks" => handlers::list_sdks_handler,
                         "/version" => handlers::version_handler,
+                        _ if path.starts_with("/sdks/") && path.ends_with("/symbols") => {
+                            handlers::find_symbol_handler
+                        }
                         _ => not_found,
                     }
                 }
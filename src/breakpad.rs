@@ -0,0 +1,196 @@
+//! Support for Breakpad's `.sym` format
+//!
+//! Breakpad tooling like `minidump_stackwalk` expects one symbol file per
+//! module in a `<name>/<id>/<name>.sym` directory layout.  Symbol files can
+//! also be read back in as a source for memdb files.
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::{Result, Error, ErrorKind};
+use super::memdb::read::MemDb;
+
+/// A parsed Breakpad symbol file
+pub struct SymFile {
+    os: String,
+    arch: String,
+    uuid: Uuid,
+    name: String,
+    symbols: Vec<(u64, u64, String)>,
+}
+
+fn bad_sym(reason: String) -> Error {
+    Error::from(ErrorKind::BadBreakpadSym(reason))
+}
+
+fn parse_hex(value: Option<&str>, line: usize) -> Result<u64> {
+    value.and_then(|x| u64::from_str_radix(x, 16).ok()).ok_or_else(|| {
+        bad_sym(format!("invalid number on line {}", line))
+    })
+}
+
+impl SymFile {
+
+    /// Parses a symbol file.
+    ///
+    /// Only the `MODULE`, `FUNC` and `PUBLIC` records are used.  `PUBLIC`
+    /// records do not carry a size so they reach up to the next symbol.
+    pub fn parse<R: BufRead>(r: R) -> Result<SymFile> {
+        let mut module = None;
+        let mut funcs = HashMap::new();
+        let mut publics = HashMap::new();
+
+        for (idx, line_rv) in r.lines().enumerate() {
+            let line = line_rv?;
+            let lineno = idx + 1;
+            let line = line.trim_right_matches('\r');
+            let mut parts = line.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some("MODULE"), Some(rest)) => {
+                    let mut parts = rest.splitn(4, ' ');
+                    let os = parts.next().unwrap_or("");
+                    let arch = parts.next().unwrap_or("");
+                    let id = parts.next().unwrap_or("");
+                    let name = parts.next().unwrap_or("");
+                    if name.is_empty() {
+                        return Err(bad_sym(format!("invalid module on line {}", lineno)));
+                    }
+                    let uuid = id.get(..32)
+                        .and_then(|x| Uuid::parse_str(x).ok())
+                        .ok_or_else(|| {
+                            bad_sym(format!("invalid module id on line {}", lineno))
+                        })?;
+                    module = Some((os.to_string(), arch.to_string(), uuid, name.to_string()));
+                }
+                (Some("FUNC"), Some(rest)) => {
+                    let rest = if rest.starts_with("m ") { &rest[2..] } else { rest };
+                    let mut parts = rest.splitn(4, ' ');
+                    let addr = parse_hex(parts.next(), lineno)?;
+                    let size = parse_hex(parts.next(), lineno)?;
+                    parts.next();
+                    let name = parts.next().unwrap_or("");
+                    funcs.insert(addr, (size, name.to_string()));
+                }
+                (Some("PUBLIC"), Some(rest)) => {
+                    let rest = if rest.starts_with("m ") { &rest[2..] } else { rest };
+                    let mut parts = rest.splitn(3, ' ');
+                    let addr = parse_hex(parts.next(), lineno)?;
+                    parts.next();
+                    let name = parts.next().unwrap_or("");
+                    publics.insert(addr, name.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let (os, arch, uuid, name) = module.ok_or_else(|| {
+            bad_sym("missing module record".into())
+        })?;
+
+        // functions win over public symbols at the same address
+        for (addr, name) in publics {
+            funcs.entry(addr).or_insert((0, name));
+        }
+        let mut symbols: Vec<_> = funcs.into_iter()
+            .map(|(addr, (size, name))| (addr, size, name))
+            .collect();
+        symbols.sort_by_key(|x| x.0);
+        for idx in 0..symbols.len() {
+            if symbols[idx].1 == 0 {
+                if let Some(next_addr) = symbols.get(idx + 1).map(|x| x.0) {
+                    symbols[idx].1 = next_addr - symbols[idx].0;
+                }
+            }
+        }
+
+        Ok(SymFile {
+            os: os,
+            arch: arch,
+            uuid: uuid,
+            name: name,
+            symbols: symbols,
+        })
+    }
+
+    /// Parses a symbol file from the file system.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<SymFile> {
+        let f = fs::File::open(path)?;
+        SymFile::parse(BufReader::new(f))
+    }
+
+    /// The operating system of the module.
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    /// The architecture of the module.
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    /// The UUID of the module.
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    /// The name of the module.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The symbols as `(addr, size, name)` sorted by address.
+    ///
+    /// A size of 0 means that the size is unknown.
+    pub fn symbols(&self) -> &[(u64, u64, String)] {
+        &self.symbols[..]
+    }
+}
+
+/// Returns the Breakpad module id for an UUID.
+///
+/// This is the UUID in uppercase hex without dashes followed by the age
+/// which is always zero for Mach-O files.
+pub fn breakpad_id(uuid: &Uuid) -> String {
+    format!("{}0", uuid.simple().to_string().to_uppercase())
+}
+
+/// Returns the file name part of an object name.
+fn module_name(object_name: &str) -> &str {
+    object_name.rsplit('/').next().unwrap_or(object_name)
+}
+
+/// Writes the symbols of one variant as Breakpad `.sym` file.
+///
+/// Mach-O symbols carry a leading underscore which Breakpad strips.
+pub fn write_sym<W: Write>(mut w: W, memdb: &MemDb, object_name: &str,
+                           arch: &str, uuid: &Uuid) -> Result<()> {
+    writeln!(w, "MODULE mac {} {} {}", arch, breakpad_id(uuid),
+             module_name(object_name))?;
+    for sym_rv in memdb.iter_symbols(uuid)? {
+        let sym = sym_rv?;
+        let name = sym.symbol();
+        writeln!(w, "PUBLIC {:x} 0 {}", sym.addr(),
+                 if name.starts_with('_') { &name[1..] } else { name })?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Exports all variants of a memdb into a Breakpad symbol directory.
+///
+/// Returns the number of files written.
+pub fn export_memdb(memdb: &MemDb, path: &Path) -> Result<usize> {
+    let variants = memdb.list_variants()?;
+    for &(name, arch, uuid) in variants.iter() {
+        let module = module_name(name);
+        let dir = path.join(module).join(breakpad_id(uuid));
+        fs::create_dir_all(&dir)?;
+        let f = fs::File::create(dir.join(format!("{}.sym", module)))?;
+        write_sym(BufWriter::new(f), memdb, name, arch, uuid)?;
+    }
+
+    Ok(variants.len())
+}
@@ -1,29 +1,38 @@
 //! This exposes the command line interface that the binary uses
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::env;
 use std::process;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::sync::Mutex;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
 
 use clap::{App, Arg, SubCommand, ArgMatches, AppSettings};
 use chrono;
 use log;
+use serde_json;
 use mime::Mime;
 use multipart::client::lazy::Multipart;
 use openssl_probe::init_ssl_cert_env_vars;
 use tempdir::TempDir;
 use console::style;
 use indicatif::HumanDuration;
+use regex::Regex;
 
 use super::{Result, ResultExt, Error};
 use super::sdk::{Sdk, SdkInfo, DumpOptions};
 use super::config::Config;
 use super::constants::VERSION;
 use super::memdb::stash::{MemDbStash, SyncOptions};
+use super::memdb::read::{MemDb, SymbolPattern};
+use super::memdb::write::merge_memdbs;
+use super::memdb::diff::MemDbDiff;
+use super::memdb::dump::{RowFormat, write_symbol_rows};
+use super::breakpad;
+use super::gsym;
 use super::api::server::{ApiServer, BindOptions};
-use super::utils::ProgressReader;
+use super::utils::{ProgressReader, file_size_format};
 use super::s3::new_hyper_client;
 
 struct SimpleLogger<W: ?Sized> {
@@ -209,11 +218,27 @@ fn execute() -> Result<()> {
                      .index(1)
                      .value_name("PATH")
                      .multiple(true)
-                     .help("Path to the support folder"))
+                     .help("Path to the support folder or a folder of Breakpad symbols"))
                 .arg(Arg::with_name("compress")
                      .short("c")
                      .long("compress")
                      .help("Write compressed files instead."))
+                .arg(Arg::with_name("front_code")
+                     .long("front-code")
+                     .help("Front-code symbol strings instead of compressing them."))
+                .arg(Arg::with_name("breakpad")
+                     .long("breakpad")
+                     .help("Convert the Breakpad .sym files in PATH instead of Mach-O files."))
+                .arg(Arg::with_name("sections")
+                     .long("section")
+                     .value_name("SEGMENT[,SECTION]")
+                     .multiple(true)
+                     .number_of_values(1)
+                     .help("Index symbols from this section (defaults to __TEXT,__text)"))
+                .arg(Arg::with_name("base")
+                     .long("base")
+                     .value_name("PATH")
+                     .help("A previous memdb of the SDK to reuse objects from"))
                 .arg(Arg::with_name("share_to")
                      .hidden(true)
                      .long("share-to")
@@ -222,19 +247,130 @@ fn execute() -> Result<()> {
                      .short("o")
                      .long("output")
                      .help("Where the result should be stored")))
+        .subcommand(
+            SubCommand::with_name("merge-memdb")
+                .about("Merges several memdb files of the same SDK into one")
+                .arg(Arg::with_name("path")
+                     .index(1)
+                     .value_name("PATH")
+                     .multiple(true)
+                     .required(true)
+                     .help("Paths to the memdb files to merge"))
+                .arg(Arg::with_name("compress")
+                     .short("c")
+                     .long("compress")
+                     .help("Write a compressed file instead."))
+                .arg(Arg::with_name("front_code")
+                     .long("front-code")
+                     .help("Front-code symbol strings instead of compressing them."))
+                .arg(Arg::with_name("output_path")
+                     .short("o")
+                     .long("output")
+                     .value_name("PATH")
+                     .required(true)
+                     .help("Where the merged file should be stored")))
+        .subcommand(
+            SubCommand::with_name("diff-memdb")
+                .about("Compares the objects of two memdb files")
+                .arg(Arg::with_name("old")
+                     .index(1)
+                     .value_name("OLD")
+                     .required(true)
+                     .help("The old SDK id or path to a memdb file"))
+                .arg(Arg::with_name("new")
+                     .index(2)
+                     .value_name("NEW")
+                     .required(true)
+                     .help("The new SDK id or path to a memdb file"))
+                .arg(Arg::with_name("json")
+                     .long("json")
+                     .help("Print the report as JSON")))
+        .subcommand(
+            SubCommand::with_name("export-memdb")
+                .about("Exports a memdb into another symbol format")
+                .arg(Arg::with_name("sdk_id")
+                     .index(1)
+                     .value_name("SDK_ID")
+                     .required(true)
+                     .help("The SDK id or path to a memdb file to export"))
+                .arg(Arg::with_name("format")
+                     .long("format")
+                     .value_name("FORMAT")
+                     .possible_values(&["breakpad", "gsym"])
+                     .default_value("breakpad")
+                     .help("The format to export to"))
+                .arg(Arg::with_name("output_path")
+                     .short("o")
+                     .long("output")
+                     .value_name("PATH")
+                     .help("Where the exported files should be stored")))
         .subcommand(
             SubCommand::with_name("dump-object")
-                .about("Dumps an object from a memdb in the stash")
+                .about("Dumps an object from a memdb")
                 .arg(Arg::with_name("sdk_id")
                      .index(1)
                      .value_name("SDK_ID")
                      .required(true)
-                     .help("The SDK id to dump"))
+                     .help("The SDK id or path to a memdb file to dump"))
                 .arg(Arg::with_name("name_or_uuid")
                      .index(2)
                      .value_name("NAME_OR_UUID")
                      .required(true)
                      .help("The object to dump")))
+        .subcommand(
+            SubCommand::with_name("search-symbols")
+                .about("Searches the symbols of a memdb")
+                .arg(Arg::with_name("sdk_id")
+                     .index(1)
+                     .value_name("SDK_ID")
+                     .required(true)
+                     .help("The SDK id or path to a memdb file to search"))
+                .arg(Arg::with_name("pattern")
+                     .index(2)
+                     .value_name("PATTERN")
+                     .required(true)
+                     .help("The prefix (or regex) to search for"))
+                .arg(Arg::with_name("regex")
+                     .short("r")
+                     .long("regex")
+                     .help("Interpret the pattern as regular expression"))
+                .arg(Arg::with_name("offset")
+                     .long("offset")
+                     .value_name("COUNT")
+                     .help("Skips this many results"))
+                .arg(Arg::with_name("limit")
+                     .long("limit")
+                     .value_name("COUNT")
+                     .help("Returns at most this many results (defaults to 100)")))
+        .subcommand(
+            SubCommand::with_name("dump-memdb")
+                .about("Dumps all symbols of a memdb")
+                .arg(Arg::with_name("sdk_id")
+                     .index(1)
+                     .value_name("SDK_ID")
+                     .required(true)
+                     .help("The SDK id or path to a memdb file to dump"))
+                .arg(Arg::with_name("format")
+                     .long("format")
+                     .value_name("FORMAT")
+                     .possible_values(&["json", "csv"])
+                     .default_value("json")
+                     .help("The output format (json writes one object per line)")))
+        .subcommand(
+            SubCommand::with_name("memdb-info")
+                .about("Prints statistics about a memdb")
+                .arg(Arg::with_name("sdk_id")
+                     .index(1)
+                     .value_name("SDK_ID")
+                     .required(true)
+                     .help("The SDK id or path to a memdb file"))
+                .arg(Arg::with_name("top")
+                     .long("top")
+                     .value_name("COUNT")
+                     .help("How many of the largest objects to list (defaults to 10)")))
+        .subcommand(
+            SubCommand::with_name("verify-memdb")
+                .about("Validates all memdb files in the stash and verifies their checksums"))
         .subcommand(
             SubCommand::with_name("sdk-fuzzy-match")
                 .about("Given an SDK ID finds the fuzzy matches in order of quality")
@@ -267,10 +403,59 @@ fn execute() -> Result<()> {
                  Path::new(matches.value_of("output_path").unwrap_or(".")))
             }
         };
-        convert_sdk_action(paths, output_path, compress, share_to)?;
+        let mut options = DumpOptions {
+            compress: compress,
+            front_code_symbols: matches.is_present("front_code"),
+            breakpad: matches.is_present("breakpad"),
+            ..Default::default()
+        };
+        if let Some(sections) = matches.values_of("sections") {
+            options.sections = sections.map(|x| x.to_string()).collect();
+        }
+        convert_sdk_action(paths, output_path, options, share_to,
+                           matches.value_of("base").map(|x| Path::new(x)))?;
+    } else if let Some(matches) = matches.subcommand_matches("merge-memdb") {
+        merge_memdb_action(matches.values_of("path").unwrap().map(|x| PathBuf::from(x)).collect(),
+                           Path::new(matches.value_of("output_path").unwrap()),
+                           DumpOptions {
+                               compress: matches.is_present("compress"),
+                               front_code_symbols: matches.is_present("front_code"),
+                               ..Default::default()
+                           })?;
+    } else if let Some(matches) = matches.subcommand_matches("diff-memdb") {
+        diff_memdb_action(&cfg, matches.value_of("old").unwrap(),
+                          matches.value_of("new").unwrap(),
+                          matches.is_present("json"))?;
+    } else if let Some(matches) = matches.subcommand_matches("export-memdb") {
+        export_memdb_action(&cfg, matches.value_of("sdk_id").unwrap(),
+                            matches.value_of("format").unwrap(),
+                            Path::new(matches.value_of("output_path").unwrap_or(".")))?;
     } else if let Some(matches) = matches.subcommand_matches("dump-object") {
         dump_object_action(&cfg, matches.value_of("sdk_id").unwrap(),
                            matches.value_of("name_or_uuid").unwrap())?;
+    } else if let Some(matches) = matches.subcommand_matches("search-symbols") {
+        let offset: usize = match matches.value_of("offset") {
+            Some(value) => value.parse().chain_err(|| "invalid value for offset")?,
+            None => 0,
+        };
+        let limit: usize = match matches.value_of("limit") {
+            Some(value) => value.parse().chain_err(|| "invalid value for limit")?,
+            None => 100,
+        };
+        search_symbols_action(&cfg, matches.value_of("sdk_id").unwrap(),
+                              matches.value_of("pattern").unwrap(),
+                              matches.is_present("regex"), offset, limit)?;
+    } else if let Some(matches) = matches.subcommand_matches("dump-memdb") {
+        dump_memdb_action(&cfg, matches.value_of("sdk_id").unwrap(),
+                          matches.value_of("format").unwrap())?;
+    } else if let Some(matches) = matches.subcommand_matches("memdb-info") {
+        let top: usize = match matches.value_of("top") {
+            Some(value) => value.parse().chain_err(|| "invalid value for top")?,
+            None => 10,
+        };
+        memdb_info_action(&cfg, matches.value_of("sdk_id").unwrap(), top)?;
+    } else if let Some(_matches) = matches.subcommand_matches("verify-memdb") {
+        verify_memdb_action(&cfg)?;
     } else if let Some(matches) = matches.subcommand_matches("sdk-fuzzy-match") {
         sdk_fuzzy_match_action(&cfg, matches.value_of("sdk_id").unwrap())?;
     } else if let Some(matches) = matches.subcommand_matches("run") {
@@ -282,11 +467,16 @@ fn execute() -> Result<()> {
     Ok(())
 }
 
-fn convert_sdk_action(paths: Vec<PathBuf>, output_path: &Path, compress: bool,
-                      share_to: Option<&str>)
+fn convert_sdk_action(paths: Vec<PathBuf>, output_path: &Path, options: DumpOptions,
+                      share_to: Option<&str>, base_path: Option<&Path>)
     -> Result<()>
 {
     let dst_base = env::current_dir().unwrap().join(output_path);
+    let base = match base_path {
+        Some(path) => Some(MemDb::from_compressed_path(path)
+            .chain_err(|| format!("could not open {}", path.display()))?),
+        None => None,
+    };
 
     for (idx, path) in paths.iter().enumerate() {
         if idx > 0 {
@@ -294,7 +484,7 @@ fn convert_sdk_action(paths: Vec<PathBuf>, output_path: &Path, compress: bool,
         }
         let sdk = Sdk::new(&path)?;
         let mut dst = dst_base.join(sdk.info().memdb_filename());
-        if compress {
+        if options.compress {
             dst.set_extension("memdbz");
         }
 
@@ -306,12 +496,18 @@ fn convert_sdk_action(paths: Vec<PathBuf>, output_path: &Path, compress: bool,
 
         // make sure we close the file at the end, in case we want to
         // re-open it for compressing.
+        if let Some(base_path) = base_path {
+            // the base is mmapped so it must not be overwritten
+            if dst.exists() && fs::canonicalize(&dst)? == fs::canonicalize(base_path)? {
+                return Err(Error::from("The base memdb cannot be the output file"));
+            }
+        }
         let f = fs::File::create(&dst)?;
-        let options = DumpOptions {
-            compress: compress,
-            ..Default::default()
-        };
-        sdk.dump_memdb(f, options)?;
+        if let Some(ref base) = base {
+            sdk.dump_memdb_incremental(f, options.clone(), base)?;
+        } else {
+            sdk.dump_memdb(f, options.clone())?;
+        }
         println!("Dumped in {}", HumanDuration(started.elapsed()));
 
         if let Some(url) = share_to {
@@ -334,11 +530,64 @@ fn share_sdk(path: &Path, url: &str, info: &SdkInfo) -> Result<()> {
     Ok(())
 }
 
-fn dump_object_action(config: &Config, sdk_id: &str, name_or_uuid: &str) -> Result<()> {
+fn merge_memdb_action(paths: Vec<PathBuf>, output_path: &Path, options: DumpOptions)
+    -> Result<()>
+{
+    let mut memdbs = vec![];
+    for path in paths.iter() {
+        memdbs.push(MemDb::from_compressed_path(path)
+            .chain_err(|| format!("could not open {}", path.display()))?);
+    }
+
+    let started = Instant::now();
+    let f = fs::File::create(output_path)?;
+    merge_memdbs(f, options, &memdbs[..])?;
+    println!("Merged in {}", HumanDuration(started.elapsed()));
+    Ok(())
+}
+
+/// Opens a memdb either from a file or from the stash by SDK id.
+fn open_memdb(config: &Config, path_or_sdk_id: &str) -> Result<Arc<MemDb<'static>>> {
+    let path = Path::new(path_or_sdk_id);
+    if path.is_file() {
+        return Ok(Arc::new(MemDb::from_compressed_path(path)?));
+    }
     let stash = MemDbStash::new(config)?;
-    let info = SdkInfo::from_filename(sdk_id).ok_or_else(||
+    let info = SdkInfo::from_filename(path_or_sdk_id).ok_or_else(||
         Error::from("Invalid SDK ID"))?;
-    let memdb = stash.get_memdb(&info)?;
+    stash.get_memdb(&info)
+}
+
+fn diff_memdb_action(config: &Config, old: &str, new: &str, json: bool) -> Result<()> {
+    let old_memdb = open_memdb(config, old)?;
+    let new_memdb = open_memdb(config, new)?;
+    let diff = MemDbDiff::compare(&old_memdb, &new_memdb)?;
+    if json {
+        serde_json::to_writer_pretty(&mut io::stdout(), &diff)
+            .chain_err(|| "Failed to serialize report")?;
+        println!("");
+    } else {
+        println!("{}", diff);
+    }
+    Ok(())
+}
+
+fn export_memdb_action(config: &Config, sdk_id: &str, format: &str,
+                       output_path: &Path) -> Result<()> {
+    let memdb = open_memdb(config, sdk_id)?;
+    let started = Instant::now();
+    let count = match format {
+        "breakpad" => breakpad::export_memdb(&memdb, output_path)?,
+        "gsym" => gsym::export_memdb(&memdb, output_path)?,
+        _ => { return Err(Error::from(format!("Unknown format '{}'", format))); }
+    };
+    println!("Exported {} files in {}", style(count).cyan(),
+             HumanDuration(started.elapsed()));
+    Ok(())
+}
+
+fn dump_object_action(config: &Config, sdk_id: &str, name_or_uuid: &str) -> Result<()> {
+    let memdb = open_memdb(config, sdk_id)?;
     let uuid = memdb.find_uuid_fuzzy(name_or_uuid)?.ok_or_else(||
         Error::from("Object not found in SDK"))?;
 
@@ -349,6 +598,102 @@ fn dump_object_action(config: &Config, sdk_id: &str, name_or_uuid: &str) -> Resu
     Ok(())
 }
 
+fn search_symbols_action(config: &Config, sdk_id: &str, pattern: &str,
+                         is_regex: bool, offset: usize, limit: usize)
+    -> Result<()>
+{
+    let memdb = open_memdb(config, sdk_id)?;
+    let regex;
+    let pattern = if is_regex {
+        regex = Regex::new(pattern).chain_err(|| "invalid regular expression")?;
+        SymbolPattern::Regex(&regex)
+    } else {
+        SymbolPattern::Prefix(pattern)
+    };
+
+    for symbol_rv in memdb.search_symbols(pattern)?.skip(offset).take(limit) {
+        println!("{}", symbol_rv?);
+    }
+    Ok(())
+}
+
+fn dump_memdb_action(config: &Config, sdk_id: &str, format: &str) -> Result<()> {
+    let memdb = open_memdb(config, sdk_id)?;
+    let stdout = io::stdout();
+    let format = if format == "csv" { RowFormat::Csv } else { RowFormat::Json };
+    write_symbol_rows(io::BufWriter::new(stdout.lock()), &memdb, format)
+}
+
+fn memdb_info_action(config: &Config, sdk_id: &str, top: usize) -> Result<()> {
+    let memdb = open_memdb(config, sdk_id)?;
+    let stats = memdb.stats()?;
+
+    println!("SDK:           {}", style(memdb.info()).green());
+    println!("Version:       {}", stats.version);
+    println!("File size:     {}", file_size_format(stats.file_size));
+    println!("Checksum:      {}", match memdb.checksum() {
+        Some(checksum) => checksum.iter().map(|x| format!("{:02x}", x)).collect::<String>(),
+        None => "-".to_string(),
+    });
+    println!("Variants:      {}", stats.variant_count);
+    println!("UUIDs:         {}", stats.uuid_count);
+    println!("Object names:  {}", stats.object_name_count);
+    println!("Symbols:       {}", stats.symbol_count);
+    println!("Unnamed funcs: {}", stats.unnamed_function_count);
+    println!("String bytes:  {}", file_size_format(stats.string_bytes));
+    println!("Index bytes:   {}", file_size_format(stats.index_bytes));
+
+    println!("");
+    println!("Sections:");
+    for &(name, offset, count) in stats.sections.iter() {
+        println!("  {:<20} offset {:>10}  count {:>10}", name, offset, count);
+    }
+
+    let mut archs = BTreeMap::new();
+    for &(_, ref arch, _, symbol_count) in stats.objects.iter() {
+        let entry = archs.entry(arch.as_str()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += symbol_count;
+    }
+    println!("");
+    println!("Architectures:");
+    for (arch, (variant_count, symbol_count)) in archs {
+        println!("  {:<10} {:>6} variants {:>10} symbols", arch, variant_count, symbol_count);
+    }
+
+    let mut objects: Vec<_> = stats.objects.iter().collect();
+    objects.sort_by(|a, b| (b.3, &a.0).cmp(&(a.3, &b.0)));
+    println!("");
+    println!("Largest objects:");
+    for &&(ref name, ref arch, ref uuid, symbol_count) in objects.iter().take(top) {
+        println!("  {:>10} {} ({}, {})", symbol_count, name, arch, uuid);
+    }
+    Ok(())
+}
+
+fn verify_memdb_action(config: &Config) -> Result<()> {
+    let stash = MemDbStash::new(config)?;
+    let mut failed = 0;
+    for info in stash.list_sdks()? {
+        match stash.verify_memdb(&info) {
+            Ok(true) => {
+                println!("{} {}", style("OK").green(), info);
+            }
+            Ok(false) => {
+                println!("{} {}", style("NO CHECKSUM").yellow(), info);
+            }
+            Err(err) => {
+                println!("{} {}: {}", style("FAILED").red(), info, err);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::from(format!("{} memdb files failed to verify", failed)));
+    }
+    Ok(())
+}
+
 fn sdk_fuzzy_match_action(config: &Config, sdk_id: &str) -> Result<()> {
     let stash = MemDbStash::new(config)?;
     let sdk_infos = stash.fuzzy_match_sdk_id(sdk_id)?;
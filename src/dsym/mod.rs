@@ -0,0 +1,772 @@
+//! Mach-O Dsym Support
+//!
+//! This module adds support for reading macho files and to extract some
+//! limited set of debug symbols from it.  This is exclusively used for
+//! system symbols from iOS SDKs and similar where actual DWARF info is
+//! not contained, just symbol tabs.
+use std::io;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::borrow::Cow;
+use std::str::from_utf8;
+use std::collections::HashSet;
+
+use memmap;
+use uuid::Uuid;
+use mach_object::{OFile, Symbol, Section, SymbolIter, SymbolReader, DyLib,
+    LoadCommand, MachCommand, get_arch_name_from_types, get_arch_from_flag,
+    cpu_type_t, cpu_subtype_t};
+
+use super::{Result, Error, ErrorKind};
+use super::utils::read_uleb128;
+
+pub mod objc;
+
+const CPU_TYPE_ARM: cpu_type_t = 12;
+const FAT_MAGIC: u32 = 0xcafebabe;
+const FAT_MAX_ARCHS: usize = 30;
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_UUID: u32 = 0x1b;
+const LC_FUNCTION_STARTS: u32 = 0x26;
+const LC_DYLD_INFO: u32 = 0x22;
+const LC_DYLD_INFO_ONLY: u32 = 0x80000022;
+const LC_DYLD_EXPORTS_TRIE: u32 = 0x80000033;
+
+const EXPORT_SYMBOL_FLAGS_KIND_MASK: u64 = 0x03;
+const EXPORT_SYMBOL_FLAGS_KIND_REGULAR: u64 = 0x00;
+const EXPORT_SYMBOL_FLAGS_REEXPORT: u64 = 0x08;
+
+/// The section symbols are read from unless configured otherwise.
+pub const DEFAULT_SECTION: &'static str = "__TEXT,__text";
+
+enum Backing<'a> {
+    Buf(Cow<'a, [u8]>),
+    Mmap(memmap::Mmap),
+}
+
+/// Mach-O objects
+///
+/// This represents mach objects from either a mmaped file or an in-memory
+/// byte slice.
+pub struct Object<'a> {
+    backing: Backing<'a>,
+    ofile: OFile,
+    variants: Vec<Variant>,
+}
+
+/// Represents an iterator over symbols
+pub struct SymbolIterator<'a> {
+    iter: Option<SymbolIter<'a>>,
+    sections: Vec<String>,
+}
+
+/// The kind of section a symbol is defined in
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// `__TEXT,__text`
+    Text = 0,
+    /// Stubs and stub helpers in `__TEXT`
+    Stubs = 1,
+    /// Any other section in `__TEXT`
+    OtherText = 2,
+    /// Sections in the `__DATA` and `__AUTH` segments
+    Data = 3,
+    /// Anything else
+    Other = 4,
+}
+
+/// A segment from the raw load commands
+struct Segment<'a> {
+    name: &'a str,
+    vmaddr: u64,
+    vmsize: u64,
+    fileoff: u64,
+    filesize: u64,
+    sections: Vec<SegmentSection<'a>>,
+}
+
+/// A section of a `Segment`
+struct SegmentSection<'a> {
+    segname: &'a str,
+    sectname: &'a str,
+    addr: u64,
+    size: u64,
+}
+
+/// Provides access to symbols in an object
+pub struct Symbols<'a> {
+    cputype: cpu_type_t,
+    cpusubtype: cpu_subtype_t,
+    ofile: &'a OFile,
+    cursor: Cursor<&'a [u8]>,
+}
+
+/// Represents a variant in an object
+pub struct Variant {
+    cputype: cpu_type_t,
+    cpusubtype: cpu_subtype_t,
+    uuid: Option<Uuid>,
+    name: Option<String>,
+    vmaddr: u64,
+    vmsize: u64,
+}
+
+impl<'a> Symbols<'a> {
+    /// Returns the architecture for these symbols
+    pub fn arch(&self) -> &str {
+        get_arch_name_from_types(self.cputype, self.cpusubtype).unwrap_or("unknown")
+    }
+
+    /// Returns the function starts from `LC_FUNCTION_STARTS`.
+    ///
+    /// The addresses are relative to the start of the `__TEXT` segment.  If
+    /// the object does not have this load command the list is empty.  The
+    /// thumb bit of 32-bit ARM function starts is cleared.
+    pub fn function_starts(&self) -> Vec<u64> {
+        let data = *self.cursor.get_ref();
+        let mask = if self.cputype == CPU_TYPE_ARM { !1 } else { !0 };
+        let mut rv = vec![];
+        for (cmd, cmd_data) in load_commands(data) {
+            if cmd != LC_FUNCTION_STARTS {
+                continue;
+            }
+            let dataoff = read_u32(cmd_data, 8).unwrap_or(0) as usize;
+            let datasize = read_u32(cmd_data, 12).unwrap_or(0) as usize;
+            if dataoff > data.len() || data.len() - dataoff < datasize {
+                break;
+            }
+            rv.extend(decode_function_starts(&data[dataoff..dataoff + datasize])
+                .into_iter().map(|addr| addr & mask));
+        }
+        rv
+    }
+
+    /// Returns the symbols exported through the dyld export trie.
+    ///
+    /// This also works for images with stripped symbol tables.  Addresses
+    /// are relative to the start of the `__TEXT` segment.  Only regular
+    /// exports in the given sections are returned (see `iter_sections`).
+    pub fn exports(&self, sections: &[String]) -> Vec<(u64, String, SectionKind)> {
+        let data = *self.cursor.get_ref();
+        let mut trie = None;
+        for (cmd, cmd_data) in load_commands(data) {
+            let (off, size) = match cmd {
+                LC_DYLD_INFO | LC_DYLD_INFO_ONLY => {
+                    (read_u32(cmd_data, 40), read_u32(cmd_data, 44))
+                }
+                LC_DYLD_EXPORTS_TRIE => {
+                    (read_u32(cmd_data, 8), read_u32(cmd_data, 12))
+                }
+                _ => { continue; }
+            };
+            if let (Some(off), Some(size)) = (off, size) {
+                let (off, size) = (off as usize, size as usize);
+                if size > 0 && off <= data.len() && data.len() - off >= size {
+                    trie = Some(&data[off..off + size]);
+                }
+            }
+        }
+
+        match trie {
+            Some(trie) => self.filter_sections(walk_export_trie(trie), sections),
+            None => vec![],
+        }
+    }
+
+    /// Returns Objective-C methods recovered from the class metadata.
+    ///
+    /// Names are formatted as `-[Class selector]` or `+[Class selector]`.
+    /// Addresses are relative to the start of the `__TEXT` segment.  Only
+    /// methods in the given sections are returned (see `iter_sections`).
+    pub fn objc_methods(&self, sections: &[String]) -> Vec<(u64, String, SectionKind)> {
+        self.filter_sections(objc::methods(*self.cursor.get_ref()), sections)
+    }
+
+    /// Returns the end addresses of the segments holding any of the given
+    /// sections.
+    ///
+    /// Addresses are relative to the start of the `__TEXT` segment.  No
+    /// symbol in these sections reaches past the end of its segment.
+    pub fn segment_ends(&self, sections: &[String]) -> Vec<u64> {
+        let segments = read_segments(*self.cursor.get_ref());
+        let text_addr = segments.iter().find(|x| x.name == "__TEXT").map_or(0, |x| x.vmaddr);
+        segments.iter()
+            .filter(|seg| seg.sections.iter().any(|x| {
+                is_section_allowed(sections, x.segname, x.sectname)
+            }))
+            .filter_map(|seg| {
+                seg.vmaddr.checked_add(seg.vmsize).and_then(|end| end.checked_sub(text_addr))
+            })
+            .collect()
+    }
+
+    /// Keeps the symbols in the given sections and classifies them.
+    ///
+    /// Addresses are relative to the start of the `__TEXT` segment.
+    fn filter_sections(&self, symbols: Vec<(u64, String)>, sections: &[String])
+        -> Vec<(u64, String, SectionKind)>
+    {
+        let segments = read_segments(*self.cursor.get_ref());
+        let text_addr = segments.iter().find(|x| x.name == "__TEXT").map_or(0, |x| x.vmaddr);
+        let mut rv = vec![];
+        for (addr, name) in symbols {
+            let abs_addr = text_addr.wrapping_add(addr);
+            let section = segments.iter().flat_map(|x| x.sections.iter()).find(|x| {
+                abs_addr >= x.addr && abs_addr - x.addr < x.size
+            });
+            if let Some(section) = section {
+                if is_section_allowed(sections, section.segname, section.sectname) {
+                    rv.push((addr, name,
+                             SectionKind::from_names(section.segname, section.sectname)));
+                }
+            }
+        }
+        rv
+    }
+
+    /// Returns an iterator over the symbols in `__TEXT,__text`.
+    pub fn iter(&'a mut self) -> SymbolIterator<'a> {
+        self.iter_sections(&[DEFAULT_SECTION.to_string()])
+    }
+
+    /// Returns an iterator over the symbols in the given sections.
+    ///
+    /// Sections are given as `SEGMENT,SECTION` or just `SEGMENT` to include
+    /// all sections of a segment.
+    pub fn iter_sections(&'a mut self, sections: &[String]) -> SymbolIterator<'a> {
+        SymbolIterator {
+            iter: self.ofile.symbols(&mut self.cursor),
+            sections: sections.to_vec(),
+        }
+    }
+}
+
+impl<'a> SymbolIterator<'a> {
+    /// Like `next` but also returns the kind of section of the symbol.
+    pub fn next_with_kind(&mut self) -> Option<(u64, &'a str, SectionKind)> {
+        let iter = try_opt!(self.iter.as_mut());
+        while let Some(sym) = iter.next() {
+            if let Symbol::Defined { ref name, ref section, entry, .. } = sym {
+                if name.is_some() {
+                    if let &Some(ref sect) = section {
+                        let Section { ref sectname, ref segname, .. } = **sect;
+                        if is_section_allowed(&self.sections, segname, sectname) {
+                            return Some((entry as u64, name.unwrap(),
+                                         SectionKind::from_names(segname, sectname)));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for SymbolIterator<'a> {
+    type Item = (u64, &'a str);
+
+    fn next(&mut self) -> Option<(u64, &'a str)> {
+        self.next_with_kind().map(|(addr, name, _)| (addr, name))
+    }
+}
+
+impl SectionKind {
+    /// Classifies a section by its segment and section name.
+    pub fn from_names(segname: &str, sectname: &str) -> SectionKind {
+        match (segname, sectname) {
+            ("__TEXT", "__text") => SectionKind::Text,
+            ("__TEXT", "__stubs") | ("__TEXT", "__auth_stubs") |
+            ("__TEXT", "__stub_helper") | ("__TEXT", "__objc_stubs") => SectionKind::Stubs,
+            ("__TEXT", _) => SectionKind::OtherText,
+            _ if segname.starts_with("__DATA") || segname.starts_with("__AUTH") => {
+                SectionKind::Data
+            }
+            _ => SectionKind::Other,
+        }
+    }
+
+    /// Converts a stored kind back.  Unknown values become `Other`.
+    pub fn from_u8(value: u8) -> SectionKind {
+        match value {
+            0 => SectionKind::Text,
+            1 => SectionKind::Stubs,
+            2 => SectionKind::OtherText,
+            3 => SectionKind::Data,
+            _ => SectionKind::Other,
+        }
+    }
+
+    /// A short name for the kind as used in the API.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SectionKind::Text => "text",
+            SectionKind::Stubs => "stubs",
+            SectionKind::OtherText => "other_text",
+            SectionKind::Data => "data",
+            SectionKind::Other => "other",
+        }
+    }
+}
+
+fn is_section_allowed(sections: &[String], segname: &str, sectname: &str) -> bool {
+    sections.iter().any(|section| {
+        let mut parts = section.splitn(2, ',');
+        parts.next() == Some(segname) && parts.next().map_or(true, |x| x == sectname)
+    })
+}
+
+/// Decodes the delta encoded addresses of a function starts blob.
+///
+/// Decoding stops at the terminating zero or if an address overflows.
+fn decode_function_starts(starts: &[u8]) -> Vec<u64> {
+    let mut rv = vec![];
+    let mut offset = 0;
+    let mut addr = 0u64;
+    while let Some(delta) = read_uleb128(starts, &mut offset) {
+        if delta == 0 {
+            break;
+        }
+        addr = match addr.checked_add(delta) {
+            Some(addr) => addr,
+            None => break,
+        };
+        rv.push(addr);
+    }
+    rv
+}
+
+/// Walks a dyld export trie and returns the image offsets and names of
+/// all regular exports.
+fn walk_export_trie(trie: &[u8]) -> Vec<(u64, String)> {
+    let mut rv = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![(0, vec![])];
+    while let Some((node, prefix)) = stack.pop() {
+        // a malformed trie could otherwise send us in circles
+        if !visited.insert(node) {
+            continue;
+        }
+        let mut offset = node;
+        let terminal_size = match read_uleb128(trie, &mut offset) {
+            Some(size) => size as usize,
+            None => { continue; }
+        };
+        let children_offset = offset.saturating_add(terminal_size);
+        if terminal_size > 0 {
+            let flags = read_uleb128(trie, &mut offset).unwrap_or(0);
+            if flags & EXPORT_SYMBOL_FLAGS_REEXPORT == 0 &&
+               flags & EXPORT_SYMBOL_FLAGS_KIND_MASK == EXPORT_SYMBOL_FLAGS_KIND_REGULAR {
+                if let (Some(addr), Ok(name)) = (read_uleb128(trie, &mut offset),
+                                                 from_utf8(&prefix)) {
+                    rv.push((addr, name.to_string()));
+                }
+            }
+        }
+
+        let mut offset = children_offset;
+        let child_count = match trie.get(offset) {
+            Some(&count) => count,
+            None => { continue; }
+        };
+        offset += 1;
+        for _ in 0..child_count {
+            if offset > trie.len() {
+                break;
+            }
+            let label_len = match trie[offset..].iter().position(|&x| x == 0) {
+                Some(len) => len,
+                None => { break; }
+            };
+            let mut label = prefix.clone();
+            label.extend_from_slice(&trie[offset..offset + label_len]);
+            offset += label_len + 1;
+            match read_uleb128(trie, &mut offset) {
+                Some(child) => stack.push((child as usize, label)),
+                None => { break; }
+            }
+        }
+    }
+    rv
+}
+
+/// Returns the segments of a thin mach-o file.
+fn read_segments(data: &[u8]) -> Vec<Segment> {
+    load_commands(data).into_iter()
+        .filter_map(|(cmd, cmd_data)| match cmd {
+            LC_SEGMENT | LC_SEGMENT_64 => read_segment(cmd == LC_SEGMENT_64, cmd_data),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads a `LC_SEGMENT` or `LC_SEGMENT_64` command.
+fn read_segment(is_64: bool, data: &[u8]) -> Option<Segment> {
+    let name = try_opt!(read_name(data, 8));
+    let (vmaddr, vmsize, fileoff, filesize, nsects, mut offset, section_size) = if is_64 {
+        (try_opt!(read_u64(data, 24)), try_opt!(read_u64(data, 32)),
+         try_opt!(read_u64(data, 40)), try_opt!(read_u64(data, 48)),
+         try_opt!(read_u32(data, 64)), 72, 80)
+    } else {
+        (try_opt!(read_u32(data, 24)) as u64, try_opt!(read_u32(data, 28)) as u64,
+         try_opt!(read_u32(data, 32)) as u64, try_opt!(read_u32(data, 36)) as u64,
+         try_opt!(read_u32(data, 48)), 56, 68)
+    };
+    let mut sections = vec![];
+    for _ in 0..nsects {
+        let (addr, size) = if is_64 {
+            (try_opt!(read_u64(data, offset + 32)), try_opt!(read_u64(data, offset + 40)))
+        } else {
+            (try_opt!(read_u32(data, offset + 32)) as u64,
+             try_opt!(read_u32(data, offset + 36)) as u64)
+        };
+        sections.push(SegmentSection {
+            segname: try_opt!(read_name(data, offset + 16)),
+            sectname: try_opt!(read_name(data, offset)),
+            addr: addr,
+            size: size,
+        });
+        offset += section_size;
+    }
+    Some(Segment {
+        name: name,
+        vmaddr: vmaddr,
+        vmsize: vmsize,
+        fileoff: fileoff,
+        filesize: filesize,
+        sections: sections,
+    })
+}
+
+/// Reads a NUL padded 16 byte name.
+fn read_name(data: &[u8], offset: usize) -> Option<&str> {
+    if offset > data.len() || data.len() - offset < 16 {
+        return None;
+    }
+    let name = &data[offset..offset + 16];
+    let len = name.iter().position(|&x| x == 0).unwrap_or(16);
+    from_utf8(&name[..len]).ok()
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let low = try_opt!(read_u32(data, offset)) as u64;
+    let high = try_opt!(read_u32(data, offset.saturating_add(4))) as u64;
+    Some(low | high << 32)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    if offset > data.len() || data.len() - offset < 4 {
+        return None;
+    }
+    Some((data[offset] as u32) |
+         (data[offset + 1] as u32) << 8 |
+         (data[offset + 2] as u32) << 16 |
+         (data[offset + 3] as u32) << 24)
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    read_u32(data, offset).map(|x| x.swap_bytes())
+}
+
+/// Fills the buffer and returns `false` if the reader ends before.
+fn read_exact_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<bool> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Reads the UUIDs of all variants of a mach-o file.
+///
+/// Only the headers and load commands are read which is a lot cheaper
+/// than parsing the object.  Other files have no UUIDs, this includes
+/// Java class files which share the fat magic but claim too many archs.
+pub fn read_uuids<R: Read + Seek>(mut r: R) -> Result<Vec<Uuid>> {
+    let mut header = vec![0; 32];
+    if !read_exact_or_eof(&mut r, &mut header[..8])? {
+        return Ok(vec![]);
+    }
+
+    let mut offsets = vec![];
+    if read_u32_be(&header, 0) == Some(FAT_MAGIC) {
+        let count = read_u32_be(&header, 4).unwrap_or(0) as usize;
+        if count > FAT_MAX_ARCHS {
+            return Ok(vec![]);
+        }
+        let mut archs = vec![0; count * 20];
+        if !read_exact_or_eof(&mut r, &mut archs)? {
+            return Ok(vec![]);
+        }
+        for idx in 0..count {
+            offsets.push(read_u32_be(&archs, idx * 20 + 8).unwrap_or(0) as u64);
+        }
+    } else {
+        offsets.push(0);
+    }
+
+    let mut rv = vec![];
+    for offset in offsets {
+        r.seek(SeekFrom::Start(offset))?;
+        header.resize(32, 0);
+        if !read_exact_or_eof(&mut r, &mut header)? {
+            continue;
+        }
+        let header_size = match read_u32(&header, 0) {
+            Some(MH_MAGIC) => 28,
+            Some(MH_MAGIC_64) => 32,
+            _ => { continue; }
+        };
+        let sizeofcmds = read_u32(&header, 20).unwrap_or(0) as u64;
+        header.truncate(header_size);
+        r.seek(SeekFrom::Start(offset + header_size as u64))?;
+        (&mut r).take(sizeofcmds).read_to_end(&mut header)?;
+        for (cmd, data) in load_commands(&header) {
+            if cmd == LC_UUID && data.len() >= 24 {
+                if let Ok(uuid) = Uuid::from_bytes(&data[8..24]) {
+                    rv.push(uuid);
+                }
+            }
+        }
+    }
+    Ok(rv)
+}
+
+/// Returns the raw load commands of a thin mach-o file as pairs of the
+/// command type and the command bytes.
+///
+/// This is used for commands the mach-o parser does not expose.  Only
+/// little endian files are supported.
+fn load_commands(data: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut rv = vec![];
+    let header_size = match read_u32(data, 0) {
+        Some(MH_MAGIC) => 28,
+        Some(MH_MAGIC_64) => 32,
+        _ => { return rv; }
+    };
+    let ncmds = read_u32(data, 16).unwrap_or(0);
+    let mut offset = header_size;
+    for _ in 0..ncmds {
+        let (cmd, cmdsize) = match (read_u32(data, offset), read_u32(data, offset + 4)) {
+            (Some(cmd), Some(cmdsize)) => (cmd, cmdsize as usize),
+            _ => { break; }
+        };
+        if cmdsize < 8 || data.len() - offset < cmdsize {
+            break;
+        }
+        rv.push((cmd, &data[offset..offset + cmdsize]));
+        offset += cmdsize;
+    }
+    rv
+}
+
+impl<'a> Backing<'a> {
+
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        match *self {
+            Backing::Buf(ref buf) => buf,
+            Backing::Mmap(ref mmap) => unsafe { mmap.as_slice() }
+        }
+    }
+
+    #[inline(always)]
+    pub fn cursor(&self, offset: usize) -> Cursor<&[u8]> {
+        Cursor::new(&self.buffer()[offset..])
+    }
+}
+
+fn extract_variant<'a>(variants: &'a mut Vec<Variant>, file: &'a OFile) {
+    if let &OFile::MachFile { ref header, ref commands, .. } = file {
+        let mut variant_uuid = None;
+        let mut variant_name = None;
+        let mut variant_vmaddr = 0;
+        let mut variant_vmsize = 0;
+        for &MachCommand(ref load_cmd, _) in commands {
+            match load_cmd {
+                &LoadCommand::Uuid(uuid) => {
+                    variant_uuid = Some(uuid);
+                },
+                &LoadCommand::IdDyLib(DyLib { ref name, .. }) => {
+                    variant_name = Some(name.1.clone());
+                }
+                &LoadCommand::Segment { ref segname, vmaddr, vmsize, .. } => {
+                    if segname == "__TEXT" {
+                        variant_vmaddr = vmaddr as u64;
+                        variant_vmsize = vmsize as u64;
+                    }
+                }
+                &LoadCommand::Segment64 { ref segname, vmaddr, vmsize, .. } => {
+                    if segname == "__TEXT" {
+                        variant_vmaddr = vmaddr as u64;
+                        variant_vmsize = vmsize as u64;
+                    }
+                }
+                _ => {}
+            }
+        }
+        variants.push(Variant {
+            cputype: header.cputype,
+            cpusubtype: header.cpusubtype,
+            uuid: variant_uuid,
+            name: variant_name,
+            vmaddr: variant_vmaddr,
+            vmsize: variant_vmsize,
+        })
+    }
+}
+
+impl<'a> Object<'a> {
+
+    fn from_backing(backing: Backing<'a>) -> Result<Object<'a>> {
+        let ofile = OFile::parse(&mut backing.cursor(0))?;
+        let mut variants = vec![];
+
+        match ofile {
+            OFile::FatFile { ref files, .. } => {
+                for &(_, ref file) in files {
+                    extract_variant(&mut variants, file);
+                }
+            }
+            OFile::MachFile { .. } => {
+                extract_variant(&mut variants, &ofile);
+            }
+            _ => {}
+        }
+
+        Ok(Object {
+            backing: backing,
+            ofile: ofile,
+            variants: variants,
+        })
+    }
+
+    fn from_cow(cow: Cow<'a, [u8]>) -> Result<Object<'a>> {
+        Object::from_backing(Backing::Buf(cow))
+    }
+
+    /// Parses a macho object from a given slice.
+    pub fn from_slice(buf: &'a [u8]) -> Result<Object<'a>> {
+        Object::from_cow(Cow::Borrowed(buf))
+    }
+
+    /// Parses a macho object from a vector.
+    pub fn from_vec(buf: Vec<u8>) -> Result<Object<'a>> {
+        Object::from_cow(Cow::Owned(buf))
+    }
+
+    /// Parses a macho object from a memory mapped file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Object<'a>> {
+        let mmap = memmap::Mmap::open_path(path, memmap::Protection::Read)?;
+        Object::from_backing(Backing::Mmap(mmap))
+    }
+
+    /// Return a slice of the variants
+    pub fn variants(&'a self) -> &'a [Variant] {
+        &self.variants[..]
+    }
+
+    /// Returns an object providing symbol access for a given
+    /// architecture.
+    ///
+    /// The architecture can be found from the provided variant.
+    pub fn symbols<'b>(&'a self, arch: &'b str) -> Result<Symbols<'a>> {
+        let &(cputype, cpusubtype) = get_arch_from_flag(arch).ok_or_else(|| {
+            Error::from(ErrorKind::UnknownArchitecture(arch.to_string()))
+        })?;
+
+        match self.ofile {
+            OFile::FatFile { ref files, .. } => {
+                for &(ref arch, ref file) in files {
+                    if arch.cputype == cputype && arch.cpusubtype == cpusubtype {
+                        return Ok(Symbols {
+                            cputype: arch.cputype,
+                            cpusubtype: arch.cpusubtype,
+                            ofile: file,
+                            cursor: self.backing.cursor(arch.offset as usize),
+                        });
+                    }
+                }
+            }
+            OFile::MachFile { ref header, .. } => {
+                if header.cputype == cputype && header.cpusubtype == cpusubtype {
+                    return Ok(Symbols {
+                        cputype: header.cputype,
+                        cpusubtype: header.cpusubtype,
+                        ofile: &self.ofile,
+                        cursor: self.backing.cursor(0),
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        return Err(ErrorKind::MissingArchitecture(arch.to_string()).into());
+    }
+}
+
+impl Variant {
+    /// Returns the architecture of this variant
+    pub fn arch(&self) -> &str {
+        get_arch_name_from_types(self.cputype, self.cpusubtype).unwrap_or("unknown")
+    }
+
+    /// Returns the contained name of the variant
+    ///
+    /// This is the full path of the debug symbol / object file if stored.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|x| x.as_str())
+    }
+
+    /// Returns the UUID of the variant
+    ///
+    /// Normally mach-o files have contained UUIDs.  In case we have one, it's
+    /// being returned here.
+    pub fn uuid(&self) -> Option<Uuid> {
+        self.uuid
+    }
+
+    /// The vmaddr as u64. Might be 0
+    pub fn vmaddr(&self) -> u64 {
+        self.vmaddr
+    }
+
+    /// The vmsize as u64. Might be 0
+    pub fn vmsize(&self) -> u64 {
+        self.vmsize
+    }
+}
+
+#[test]
+fn test_read_uuids_fat_count() {
+    let uuids = read_uuids(Cursor::new(&b"\xca\xfe\xba\xbe\xff\xff\xff\xff"[..])).unwrap();
+    assert!(uuids.is_empty());
+
+    // java class files share the magic
+    let class = b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x1d\x0a\x00\x06\x00\x0f";
+    assert!(read_uuids(Cursor::new(&class[..])).unwrap().is_empty());
+}
+
+#[test]
+fn test_decode_function_starts() {
+    let starts = [0x80, 0x20, 0x20, 0x00, 0x10];
+    assert_eq!(decode_function_starts(&starts), vec![0x1000, 0x1020]);
+
+    // overflowing deltas end the list
+    let starts = [0x80, 0x20, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x20];
+    assert_eq!(decode_function_starts(&starts), vec![0x1000]);
+}
+
+#[test]
+fn test_walk_export_trie() {
+    let trie = [0x00, 0x01, b'_', b'f', b'o', b'o', 0x00, 0x08,
+                0x02, 0x00, 0x10, 0x00];
+    assert_eq!(walk_export_trie(&trie), vec![(0x10, "_foo".to_string())]);
+
+    // a child pointing back to the root must not loop
+    let trie = [0x00, 0x01, b'_', 0x00, 0x00];
+    assert!(walk_export_trie(&trie).is_empty());
+}
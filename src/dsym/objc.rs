@@ -0,0 +1,205 @@
+//! Recovers Objective-C method names from the class metadata.
+//!
+//! Stripped binaries still carry the runtime metadata of their classes.
+//! The method lists of every class in `__objc_classlist` give us the
+//! selector and implementation of each method which is enough to name
+//! them like the symbol table would (`-[Class selector]`).  Pointers are
+//! expected to be plain addresses; chained fixups are not resolved.
+use std::cmp;
+use std::str::from_utf8;
+
+use super::{Segment, MH_MAGIC_64, read_segments, read_u32, read_u64};
+
+const FAST_DATA_MASK_64: u64 = 0x00007ffffffffff8;
+const FAST_DATA_MASK_32: u64 = 0xfffffffc;
+const METHOD_LIST_IS_SMALL: u32 = 0x80000000;
+const METHOD_LIST_ENTSIZE_MASK: u32 = 0xfffc;
+
+/// A thin mach-o image with its segments.
+struct Image<'a> {
+    data: &'a [u8],
+    segments: Vec<Segment<'a>>,
+    is_64: bool,
+}
+
+impl<'a> Image<'a> {
+
+    fn ptr_size(&self) -> u64 {
+        if self.is_64 { 8 } else { 4 }
+    }
+
+    /// Translates a virtual address into a file offset.
+    fn offset(&self, addr: u64) -> Option<usize> {
+        let seg = try_opt!(self.segments.iter().find(|x| {
+            addr >= x.vmaddr && addr - x.vmaddr < x.filesize
+        }));
+        let offset = seg.fileoff.wrapping_add(addr - seg.vmaddr) as usize;
+        if offset < self.data.len() { Some(offset) } else { None }
+    }
+
+    fn read_u32(&self, addr: u64) -> Option<u32> {
+        read_u32(self.data, try_opt!(self.offset(addr)))
+    }
+
+    fn read_ptr(&self, addr: u64) -> Option<u64> {
+        let offset = try_opt!(self.offset(addr));
+        if self.is_64 {
+            read_u64(self.data, offset)
+        } else {
+            read_u32(self.data, offset).map(|x| x as u64)
+        }
+    }
+
+    fn read_str(&self, addr: u64) -> Option<&'a str> {
+        let data = &self.data[try_opt!(self.offset(addr))..];
+        let len = try_opt!(data.iter().position(|&x| x == 0));
+        from_utf8(&data[..len]).ok()
+    }
+
+    /// Returns the addresses of all classes in `__objc_classlist`.
+    ///
+    /// The section size is not trusted beyond the end of the file.
+    fn classes(&self) -> Vec<u64> {
+        let mut rv = vec![];
+        for sect in self.segments.iter().flat_map(|x| x.sections.iter()) {
+            if sect.sectname != "__objc_classlist" {
+                continue;
+            }
+            let start = match self.offset(sect.addr) {
+                Some(start) => start,
+                None => { continue; }
+            };
+            let size = cmp::min(sect.size, (self.data.len() - start) as u64);
+            let mut pos = 0;
+            while pos < size {
+                match self.read_ptr(sect.addr.wrapping_add(pos)) {
+                    Some(class) => rv.push(class),
+                    None => { break; }
+                }
+                pos += self.ptr_size();
+            }
+        }
+        rv
+    }
+
+    /// Returns the name and method list of a class from its `class_ro_t`.
+    fn class_info(&self, class: u64) -> Option<(&'a str, u64)> {
+        let mask = if self.is_64 { FAST_DATA_MASK_64 } else { FAST_DATA_MASK_32 };
+        let ro = try_opt!(self.read_ptr(class.wrapping_add(4 * self.ptr_size()))) & mask;
+        let (name_offset, methods_offset) = if self.is_64 { (24, 32) } else { (16, 20) };
+        let name = try_opt!(self.read_str(try_opt!(self.read_ptr(ro.wrapping_add(name_offset)))));
+        Some((name, try_opt!(self.read_ptr(ro.wrapping_add(methods_offset)))))
+    }
+
+    /// Adds the methods of a method list as `(imp, selector)` pairs.
+    fn add_methods(&self, list: u64, rv: &mut Vec<(u64, &'a str)>) {
+        let flags = match self.read_u32(list) {
+            Some(flags) => flags,
+            None => { return; }
+        };
+        let count = self.read_u32(list.wrapping_add(4)).unwrap_or(0) as u64;
+        let entsize = (flags & METHOD_LIST_ENTSIZE_MASK) as u64;
+        let is_small = flags & METHOD_LIST_IS_SMALL != 0;
+        let min_entsize = if is_small { 12 } else { 3 * self.ptr_size() };
+        if count == 0 || entsize < min_entsize ||
+           self.offset(list.wrapping_add(8 + count * entsize - 1)).is_none() {
+            return;
+        }
+
+        for idx in 0..count {
+            let entry = list.wrapping_add(8 + idx * entsize);
+            let (sel, imp) = if is_small {
+                // the name points to a selector reference, all fields are
+                // relative to themselves.
+                let rel = |addr: u64| self.read_u32(addr).map(|x| {
+                    addr.wrapping_add(x as i32 as i64 as u64)
+                });
+                (rel(entry).and_then(|x| self.read_ptr(x)), rel(entry.wrapping_add(8)))
+            } else {
+                (self.read_ptr(entry), self.read_ptr(entry.wrapping_add(2 * self.ptr_size())))
+            };
+            if let (Some(sel), Some(imp)) = (sel.and_then(|x| self.read_str(x)), imp) {
+                if imp != 0 {
+                    rv.push((if self.is_64 { imp } else { imp & !1 }, sel));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the Objective-C methods of a thin mach-o image.
+///
+/// Methods are returned as `(addr, name)` with the address relative to
+/// the start of the `__TEXT` segment.  Instance methods are named
+/// `-[Class selector]` and class methods `+[Class selector]`.
+pub fn methods(data: &[u8]) -> Vec<(u64, String)> {
+    let image = Image {
+        data: data,
+        segments: read_segments(data),
+        is_64: read_u32(data, 0) == Some(MH_MAGIC_64),
+    };
+    let text_addr = match image.segments.iter().find(|x| x.name == "__TEXT") {
+        Some(seg) => seg.vmaddr,
+        None => { return vec![]; }
+    };
+
+    let mut rv = vec![];
+    for class in image.classes() {
+        let meta = image.read_ptr(class);
+        for &(prefix, class) in [("-", Some(class)), ("+", meta)].iter() {
+            let (name, list) = match class.and_then(|x| image.class_info(x)) {
+                Some(info) => info,
+                None => { continue; }
+            };
+            let mut methods = vec![];
+            if list != 0 {
+                image.add_methods(list, &mut methods);
+            }
+            for (imp, sel) in methods {
+                rv.push((imp.wrapping_sub(text_addr), format!("{}[{} {}]", prefix, name, sel)));
+            }
+        }
+    }
+    rv
+}
+
+#[test]
+fn test_methods() {
+    fn put(buf: &mut [u8], offset: usize, value: u64, size: usize) {
+        for idx in 0..size {
+            buf[offset + idx] = (value >> (idx * 8)) as u8;
+        }
+    }
+
+    // a single __TEXT segment at 0x1000 holding one class with one method
+    let mut data = vec![0u8; 0x400];
+    put(&mut data, 0, MH_MAGIC_64 as u64, 4);
+    put(&mut data, 16, 1, 4);
+    put(&mut data, 32, 0x19, 4);
+    put(&mut data, 36, 152, 4);
+    data[40..46].copy_from_slice(b"__TEXT");
+    put(&mut data, 56, 0x1000, 8);
+    put(&mut data, 80, 0x400, 8);
+    put(&mut data, 96, 1, 4);
+    data[104..120].copy_from_slice(b"__objc_classlist");
+    data[120..126].copy_from_slice(b"__DATA");
+    put(&mut data, 136, 0x1100, 8);
+    put(&mut data, 144, 8, 8);
+
+    put(&mut data, 0x100, 0x1110, 8);
+    put(&mut data, 0x110 + 32, 0x1140 | 1, 8);
+    put(&mut data, 0x140 + 24, 0x1200, 8);
+    put(&mut data, 0x140 + 32, 0x1180, 8);
+    put(&mut data, 0x180, 24, 4);
+    put(&mut data, 0x184, 1, 4);
+    put(&mut data, 0x188, 0x1210, 8);
+    put(&mut data, 0x198, 0x1300, 8);
+    data[0x200..0x203].copy_from_slice(b"Foo");
+    data[0x210..0x213].copy_from_slice(b"bar");
+
+    assert_eq!(methods(&data), vec![(0x300, "-[Foo bar]".to_string())]);
+
+    // a bogus class list size only reaches to the end of the file
+    put(&mut data, 144, 1 << 62, 8);
+    assert_eq!(methods(&data), vec![(0x300, "-[Foo bar]".to_string())]);
+}
@@ -30,8 +30,13 @@ error_chain! {
         UnsupportedMemDbVersion {
             description("unsupported memdb version")
         }
-        BadMemDb {
+        BadMemDb(reason: String) {
             description("bad memdb file")
+            display("bad memdb file: {}", reason)
+        }
+        BadBreakpadSym(reason: String) {
+            description("bad breakpad symbol file")
+            display("bad breakpad symbol file: {}", reason)
         }
         ConfigError(err: serde_yaml::Error) {
             description("failed to load config file")
@@ -0,0 +1,149 @@
+//! Support for writing LLVM GSYM files
+//!
+//! GSYM is a compact address to symbol format used by `llvm-gsymutil`.  We
+//! only write function infos with name and size and no line tables.
+//! Addresses are relative to the start of the image like in memdb files.
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::Result;
+use super::memdb::read::MemDb;
+
+const GSYM_MAGIC: u32 = 0x4753594d;
+const GSYM_VERSION: u16 = 1;
+const INFO_TYPE_END_OF_LIST: u32 = 0;
+
+/// Offsets of the fields in the header that are filled in later.
+const HEADER_NUM_ADDRESSES: usize = 16;
+const HEADER_STRTAB_OFFSET: usize = 20;
+const HEADER_STRTAB_SIZE: usize = 24;
+
+fn put_uint(buf: &mut Vec<u8>, value: u64, size: usize) {
+    for idx in 0..size {
+        buf.push((value >> (idx * 8)) as u8);
+    }
+}
+
+fn set_uint(buf: &mut [u8], offset: usize, value: u64, size: usize) {
+    for idx in 0..size {
+        buf[offset + idx] = (value >> (idx * 8)) as u8;
+    }
+}
+
+fn align(buf: &mut Vec<u8>, alignment: usize) {
+    while buf.len() % alignment != 0 {
+        buf.push(0);
+    }
+}
+
+/// Writes functions as GSYM file.
+///
+/// Functions are `(addr, size, name)` tuples.  GSYM requires unique
+/// addresses so if multiple functions share an address the first wins.
+pub fn write_gsym<W: Write>(mut w: W, uuid: &Uuid, functions: &[(u64, u64, &str)])
+    -> Result<()>
+{
+    let mut functions = functions.to_vec();
+    functions.sort_by_key(|x| x.0);
+    functions.dedup_by_key(|x| x.0);
+
+    let base_addr = functions.first().map_or(0, |x| x.0);
+    let max_offset = functions.last().map_or(0, |x| x.0 - base_addr);
+    let addr_off_size = if max_offset <= 0xff {
+        1
+    } else if max_offset <= 0xffff {
+        2
+    } else if max_offset <= 0xffffffff {
+        4
+    } else {
+        8
+    };
+
+    // the string table always starts with the empty string
+    let mut strtab = vec![0u8];
+    let mut string_offsets = HashMap::new();
+    let mut name_offsets = vec![];
+    for &(_, _, name) in functions.iter() {
+        if !string_offsets.contains_key(name) {
+            string_offsets.insert(name, strtab.len() as u64);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+        name_offsets.push(string_offsets[name]);
+    }
+
+    // header
+    let mut buf = vec![];
+    put_uint(&mut buf, GSYM_MAGIC as u64, 4);
+    put_uint(&mut buf, GSYM_VERSION as u64, 2);
+    put_uint(&mut buf, addr_off_size as u64, 1);
+    put_uint(&mut buf, uuid.as_bytes().len() as u64, 1);
+    put_uint(&mut buf, base_addr, 8);
+    put_uint(&mut buf, functions.len() as u64, 4);
+    put_uint(&mut buf, 0, 4);
+    put_uint(&mut buf, 0, 4);
+    buf.extend_from_slice(uuid.as_bytes());
+    put_uint(&mut buf, 0, 20 - uuid.as_bytes().len());
+
+    // address offsets followed by the offsets of the function infos
+    align(&mut buf, addr_off_size);
+    for &(addr, _, _) in functions.iter() {
+        put_uint(&mut buf, addr - base_addr, addr_off_size);
+    }
+    align(&mut buf, 4);
+    let info_offsets_start = buf.len();
+    put_uint(&mut buf, 0, functions.len() * 4);
+
+    // file table with just the required empty entry
+    put_uint(&mut buf, 1, 4);
+    put_uint(&mut buf, 0, 4);
+    put_uint(&mut buf, 0, 4);
+
+    let strtab_offset = buf.len() as u64;
+    buf.extend_from_slice(&strtab);
+    set_uint(&mut buf, HEADER_NUM_ADDRESSES, functions.len() as u64, 4);
+    set_uint(&mut buf, HEADER_STRTAB_OFFSET, strtab_offset, 4);
+    set_uint(&mut buf, HEADER_STRTAB_SIZE, strtab.len() as u64, 4);
+
+    // function infos
+    for (idx, &(_, size, _)) in functions.iter().enumerate() {
+        align(&mut buf, 4);
+        let offset = buf.len() as u64;
+        set_uint(&mut buf, info_offsets_start + idx * 4, offset, 4);
+        put_uint(&mut buf, if size > 0xffffffff { 0xffffffff } else { size }, 4);
+        put_uint(&mut buf, name_offsets[idx], 4);
+        put_uint(&mut buf, INFO_TYPE_END_OF_LIST as u64, 4);
+        put_uint(&mut buf, 0, 4);
+    }
+
+    w.write_all(&buf)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Exports all variants of a memdb as GSYM files.
+///
+/// Files are written as `<name>/<uuid>.gsym`.  Returns the number of files
+/// written.
+pub fn export_memdb(memdb: &MemDb, path: &Path) -> Result<usize> {
+    let variants = memdb.list_variants()?;
+    for &(name, _, uuid) in variants.iter() {
+        let mut symbols = vec![];
+        for sym_rv in memdb.iter_symbols(uuid)? {
+            symbols.push(sym_rv?);
+        }
+        let functions: Vec<_> = symbols.iter()
+            .map(|x| (x.addr(), x.size().unwrap_or(0), x.symbol()))
+            .collect();
+
+        let dir = path.join(name.rsplit('/').next().unwrap_or(name));
+        fs::create_dir_all(&dir)?;
+        let f = fs::File::create(dir.join(format!("{}.gsym", uuid)))?;
+        write_gsym(BufWriter::new(f), uuid, &functions[..])?;
+    }
+    Ok(variants.len())
+}
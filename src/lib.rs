@@ -15,6 +15,7 @@ extern crate globset;
 #[macro_use] extern crate lazy_static;
 extern crate mach_object;
 extern crate memmap;
+extern crate brotli2;
 extern crate clap;
 extern crate console;
 extern crate indicatif;
@@ -31,6 +32,7 @@ extern crate mime;
 extern crate url;
 extern crate libc;
 extern crate md5;
+extern crate ring;
 extern crate num_cpus;
 extern crate openssl_probe;
 #[macro_use] extern crate log;
@@ -47,6 +49,8 @@ pub mod config;
 pub mod s3;
 pub mod cli;
 pub mod dsym;
+pub mod breakpad;
+pub mod gsym;
 pub mod sdk;
 pub mod api;
 pub mod constants;
@@ -0,0 +1,148 @@
+//! Compares the objects of two memdb files.
+//!
+//! This is used to figure out what changed between two builds of an SDK
+//! and with that if falling back from one to the other is safe.
+use std::fmt;
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use super::read::MemDb;
+use super::super::Result;
+use super::super::sdk::SdkInfo;
+
+/// Describes one object that differs between two memdb files.
+#[derive(Serialize, Debug, Clone)]
+pub struct ObjectDiff {
+    pub name: String,
+    pub arch: String,
+    pub old_uuid: Option<Uuid>,
+    pub new_uuid: Option<Uuid>,
+    pub old_symbol_count: usize,
+    pub new_symbol_count: usize,
+}
+
+/// The differences between two memdb files.
+#[derive(Serialize, Debug, Clone)]
+pub struct MemDbDiff {
+    pub old_sdk: SdkInfo,
+    pub new_sdk: SdkInfo,
+    pub added: Vec<ObjectDiff>,
+    pub removed: Vec<ObjectDiff>,
+    pub changed: Vec<ObjectDiff>,
+    pub unchanged_count: usize,
+    pub old_symbol_count: usize,
+    pub new_symbol_count: usize,
+}
+
+fn symbol_count(memdb: &MemDb, uuid: Option<&Uuid>) -> Result<usize> {
+    let mut rv = 0;
+    if let Some(uuid) = uuid {
+        for sym_rv in memdb.iter_symbols(uuid)? {
+            sym_rv?;
+            rv += 1;
+        }
+    }
+    Ok(rv)
+}
+
+fn object_uuids<'a>(memdb: &'a MemDb) -> Result<BTreeMap<(&'a str, &'a str), &'a Uuid>> {
+    Ok(memdb.list_object_names()?.into_iter()
+        .map(|(name, arch, uuid)| ((name, arch), uuid))
+        .collect())
+}
+
+impl ObjectDiff {
+    /// The change in the number of symbols.
+    pub fn symbol_count_delta(&self) -> i64 {
+        self.new_symbol_count as i64 - self.old_symbol_count as i64
+    }
+}
+
+impl MemDbDiff {
+    /// Compares the objects of two memdb files by name and architecture.
+    pub fn compare(old: &MemDb, new: &MemDb) -> Result<MemDbDiff> {
+        let old_objects = object_uuids(old)?;
+        let new_objects = object_uuids(new)?;
+        let mut rv = MemDbDiff {
+            old_sdk: old.info().clone(),
+            new_sdk: new.info().clone(),
+            added: vec![],
+            removed: vec![],
+            changed: vec![],
+            unchanged_count: 0,
+            old_symbol_count: 0,
+            new_symbol_count: 0,
+        };
+
+        let mut keys: Vec<_> = old_objects.keys().chain(new_objects.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            let old_uuid = old_objects.get(key).map(|&x| x);
+            let new_uuid = new_objects.get(key).map(|&x| x);
+            let old_symbol_count = symbol_count(old, old_uuid)?;
+            let new_symbol_count = symbol_count(new, new_uuid)?;
+            rv.old_symbol_count += old_symbol_count;
+            rv.new_symbol_count += new_symbol_count;
+            if old_uuid == new_uuid {
+                rv.unchanged_count += 1;
+                continue;
+            }
+            let object_diff = ObjectDiff {
+                name: key.0.to_string(),
+                arch: key.1.to_string(),
+                old_uuid: old_uuid.map(|x| *x),
+                new_uuid: new_uuid.map(|x| *x),
+                old_symbol_count: old_symbol_count,
+                new_symbol_count: new_symbol_count,
+            };
+            if old_uuid.is_none() {
+                rv.added.push(object_diff);
+            } else if new_uuid.is_none() {
+                rv.removed.push(object_diff);
+            } else {
+                rv.changed.push(object_diff);
+            }
+        }
+
+        Ok(rv)
+    }
+
+    /// Checks if the new SDK can stand in for the old one.
+    ///
+    /// This is the case if no object was removed and no object changed its
+    /// UUID so that every lookup against the old SDK also works against the
+    /// new one.
+    pub fn is_compatible(&self) -> bool {
+        self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for MemDbDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Comparing {} to {}", self.old_sdk, self.new_sdk)?;
+        for (title, objects) in vec![("Added", &self.added),
+                                     ("Removed", &self.removed),
+                                     ("Changed", &self.changed)] {
+            if objects.is_empty() {
+                continue;
+            }
+            writeln!(f, "")?;
+            writeln!(f, "{} objects:", title)?;
+            for obj in objects.iter() {
+                writeln!(f, "  {}:{} ({} -> {}, {:+} symbols)", obj.name, obj.arch,
+                         obj.old_uuid.map_or("-".to_string(), |x| x.to_string()),
+                         obj.new_uuid.map_or("-".to_string(), |x| x.to_string()),
+                         obj.symbol_count_delta())?;
+            }
+        }
+        writeln!(f, "")?;
+        writeln!(f, "{} added, {} removed, {} changed, {} unchanged",
+                 self.added.len(), self.removed.len(), self.changed.len(),
+                 self.unchanged_count)?;
+        writeln!(f, "Symbols: {} -> {}", self.old_symbol_count, self.new_symbol_count)?;
+        write!(f, "Compatible: {}", if self.is_compatible() { "yes" } else { "no" })
+    }
+}
@@ -0,0 +1,81 @@
+//! Dumps the symbols of a memdb as rows for analysis.
+//!
+//! Every symbol becomes one row with its object, architecture, UUID,
+//! address and size.  Rows are written as JSON lines or CSV.
+use std::io::Write;
+
+use serde_json;
+
+use super::read::MemDb;
+use super::super::{Result, ResultExt};
+
+/// The format rows are written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RowFormat {
+    /// One JSON object per line
+    Json,
+    /// Comma separated values with a header line
+    Csv,
+}
+
+/// A row of `dump-memdb`
+#[derive(Serialize, Debug)]
+pub struct SymbolRow<'a> {
+    pub object_name: &'a str,
+    pub arch: &'a str,
+    pub uuid: String,
+    pub addr: u64,
+    pub size: Option<u64>,
+    pub symbol: &'a str,
+}
+
+/// Quotes a CSV field if necessary.
+fn csv_field(value: &str) -> String {
+    if value.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl<'a> SymbolRow<'a> {
+    /// Writes the row as a single line.
+    pub fn write<W: Write>(&self, mut w: W, format: RowFormat) -> Result<()> {
+        match format {
+            RowFormat::Csv => {
+                writeln!(w, "{},{},{},{},{},{}",
+                         csv_field(self.object_name), csv_field(self.arch), self.uuid,
+                         self.addr, self.size.map_or("".to_string(), |x| x.to_string()),
+                         csv_field(self.symbol))?;
+            }
+            RowFormat::Json => {
+                serde_json::to_writer(&mut w, self)
+                    .chain_err(|| "Failed to serialize symbol")?;
+                writeln!(w, "")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes all symbols of all variants as rows.
+pub fn write_symbol_rows<W: Write>(mut w: W, memdb: &MemDb, format: RowFormat) -> Result<()> {
+    if format == RowFormat::Csv {
+        writeln!(w, "object_name,arch,uuid,addr,size,symbol")?;
+    }
+    for (object_name, arch, uuid) in memdb.list_variants()? {
+        for sym_rv in memdb.iter_symbols(uuid)? {
+            let sym = sym_rv?;
+            SymbolRow {
+                object_name: object_name,
+                arch: arch,
+                uuid: uuid.to_string(),
+                addr: sym.addr(),
+                size: sym.size(),
+                symbol: sym.symbol(),
+            }.write(&mut w, format)?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
@@ -3,3 +3,5 @@ pub mod read;
 pub mod write;
 pub mod types;
 pub mod stash;
+pub mod diff;
+pub mod dump;
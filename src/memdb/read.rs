@@ -4,34 +4,115 @@
 //! in-memory database format which is a flat file on the file system
 //! that gets mmaped into the process.
 use std::str::from_utf8;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 use std::mem;
 use std::slice;
 use std::path::Path;
 use std::borrow::Cow;
-use std::ffi::CStr;
-use std::os::raw::c_char;
+use std::collections::HashSet;
 
 use std::fmt;
 use uuid::Uuid;
+use regex::Regex;
 use memmap::{Mmap, Protection};
+use brotli2::read::BrotliDecoder;
+use xz2::read::XzDecoder;
+use ring::digest;
 
-use super::types::{IndexItem, StoredSlice, MemDbHeader, IndexedUuid};
-use super::super::{Result, ErrorKind};
+use super::types::{IndexItem, IndexItemV2, StoredSlice, StoredSliceV2,
+                   MemDbHeader, MemDbHeaderV2, IndexedUuid, IndexedUuidV2,
+                   IndexedObjectName, IndexedSymbolName, VariantInfo,
+                   MEMDB_VERSION};
+use super::super::{Result, Error, ErrorKind};
 use super::super::sdk::SdkInfo;
-use super::super::utils::binsearch_by_key;
+use super::super::dsym::SectionKind;
+use super::super::utils::{binsearch_by_key, binsearch_pos_by_key, read_uleb128};
 
 
+/// The magic bytes XZ compressed files start with.
+const XZ_MAGIC: &'static [u8; 6] = b"\xfd7zXZ\x00";
+
 enum Backing<'a> {
     Buf(Cow<'a, [u8]>),
     Mmap(Mmap),
 }
 
+/// Where the sections of a memdb file are, independent of the format
+/// version of the file.
+struct Layout {
+    version: u32,
+    header_size: usize,
+    checksum: Option<[u8; 32]>,
+    variants_start: usize,
+    variants_count: usize,
+    uuids_start: usize,
+    uuids_count: usize,
+    tagged_object_names_start: usize,
+    tagged_object_names_end: usize,
+    object_names_start: usize,
+    object_names_count: usize,
+    symbols_start: usize,
+    symbols_count: usize,
+    object_name_index_start: usize,
+    object_name_index_count: usize,
+    symbol_name_index_start: usize,
+    symbol_name_index_count: usize,
+    variant_info_start: usize,
+    variant_info_count: usize,
+    symbol_groups_start: usize,
+    symbol_groups_count: usize,
+    symbol_restart_interval: usize,
+}
+
+/// A view on the symbol index of a variant
+enum IndexView<'a> {
+    V2(&'a [IndexItemV2]),
+    V3(&'a [IndexItem]),
+}
+
+/// A view on the UUID index
+enum UuidView<'a> {
+    V2(&'a [IndexedUuidV2]),
+    V3(&'a [IndexedUuid]),
+}
+
+/// A view on a table of stored slices
+enum SliceView<'a> {
+    V2(&'a [StoredSliceV2]),
+    V3(&'a [StoredSlice]),
+}
+
 /// Provides access to a memdb file
 pub struct MemDb<'a> {
     info: SdkInfo,
+    layout: Layout,
     backing: Backing<'a>
 }
 
+/// Size and count statistics of a memdb file
+#[derive(Debug)]
+pub struct MemDbStats {
+    /// The format version.
+    pub version: u32,
+    /// The size of the file in bytes.
+    pub file_size: usize,
+    /// The sections of the file as name, offset and item count.
+    pub sections: Vec<(&'static str, usize, usize)>,
+    pub variant_count: usize,
+    pub uuid_count: usize,
+    pub object_name_count: usize,
+    pub symbol_count: usize,
+    /// The number of functions without symbol over all variants.
+    pub unnamed_function_count: usize,
+    /// The stored size of all strings.
+    pub string_bytes: usize,
+    /// The size of all indexes.
+    pub index_bytes: usize,
+    /// All variants with their name, architecture, UUID and symbol count.
+    pub objects: Vec<(String, String, Uuid, usize)>,
+}
+
 /// Represents a symbol from a memdb file.
 #[derive(Debug)]
 pub struct Symbol<'a> {
@@ -39,31 +120,118 @@ pub struct Symbol<'a> {
     object_name: Cow<'a, str>,
     symbol: Cow<'a, str>,
     addr: u64,
+    size: Option<u64>,
+    offset: u64,
+    section_kind: SectionKind,
+}
+
+/// Describes the image of a variant in a memdb file.
+#[derive(Debug)]
+pub struct ObjectInfo<'a> {
+    uuid: Uuid,
+    arch: &'a str,
+    vmaddr: u64,
+    vmsize: u64,
+    path: Cow<'a, str>,
 }
 
 /// Represents a symbol iterator
 pub struct SymbolIter<'a> {
     memdb: &'a MemDb<'a>,
     uuid: &'a Uuid,
-    index: &'a [IndexItem],
+    index: IndexView<'a>,
     pos: usize,
 }
 
+/// What symbol strings are matched against in a search.
+pub enum SymbolPattern<'a> {
+    /// Matches all symbols starting with a prefix.
+    Prefix(&'a str),
+    /// Matches all symbols that contain a match of a regular expression.
+    Regex(&'a Regex),
+}
+
+/// Iterates over the symbol strings matching a pattern
+pub struct SymbolSearchIter<'a> {
+    memdb: &'a MemDb<'a>,
+    pattern: SymbolPattern<'a>,
+    pos: usize,
+    indexed: bool,
+    last_sym_id: Option<u32>,
+}
+
+impl<'a> SymbolPattern<'a> {
+
+    /// Checks if a symbol matches the pattern.
+    pub fn matches(&self, symbol: &str) -> bool {
+        match *self {
+            SymbolPattern::Prefix(prefix) => symbol.starts_with(prefix),
+            SymbolPattern::Regex(regex) => regex.is_match(symbol),
+        }
+    }
+}
+
+impl<'a> SymbolSearchIter<'a> {
+
+    /// Walks the symbol name index from the first possible match.  As the
+    /// index is sorted by name the first mismatch ends the search.
+    fn next_indexed(&mut self) -> Option<Result<Cow<'a, str>>> {
+        let index = iter_try!(self.memdb.symbol_name_index());
+        loop {
+            let sym_id = try_opt!(index.get(self.pos)).sym_id();
+            self.pos += 1;
+            // all entries for a name share the same symbol
+            if self.last_sym_id == Some(sym_id) {
+                continue;
+            }
+            self.last_sym_id = Some(sym_id);
+            let symbol = iter_try!(self.memdb.get_symbol_string(sym_id));
+            if self.pattern.matches(&symbol) {
+                return Some(Ok(symbol));
+            }
+            self.pos = index.len();
+            return None;
+        }
+    }
+}
+
+impl<'a> Iterator for SymbolSearchIter<'a> {
+    type Item = Result<Cow<'a, str>>;
+
+    fn next(&mut self) -> Option<Result<Cow<'a, str>>> {
+        if self.indexed {
+            return self.next_indexed();
+        }
+        loop {
+            if self.pos >= self.memdb.layout.symbols_count {
+                return None;
+            }
+            let rv = self.memdb.get_symbol_string(self.pos as u32);
+            self.pos += 1;
+            match rv {
+                Ok(symbol) => {
+                    if self.pattern.matches(&symbol) {
+                        return Some(Ok(symbol));
+                    }
+                }
+                Err(err) => { return Some(Err(err)); }
+            }
+        }
+    }
+}
+
 impl<'a> Iterator for SymbolIter<'a> {
     type Item = Result<Symbol<'a>>;
 
     fn next(&mut self) -> Option<Result<Symbol<'a>>> {
         loop {
-            if self.pos < self.index.len() {
-                let ii = &self.index[self.pos];
-                self.pos += 1;
-                match self.memdb.index_item_to_symbol(ii, self.uuid) {
-                    Ok(Some(sym)) => { return Some(Ok(sym)); }
-                    Ok(None) => { continue; }
-                    Err(err) => { return Some(Err(err)); }
-                }
-            } else {
-                return None;
+            let ii = try_opt!(self.index.get(self.pos));
+            let size = self.index.symbol_size(self.pos);
+            self.pos += 1;
+            match self.memdb.index_item_to_symbol(&ii, self.uuid, size, ii.addr()) {
+                Ok(Some(sym)) => { return Some(Ok(sym)); }
+                Ok(None) => { continue; }
+                Err(err) => { return Some(Err(err)); }
             }
         }
     }
@@ -80,31 +248,36 @@ impl<'a> fmt::Display for Symbol<'a> {
 
 impl<'a> Backing<'a> {
 
+    fn is_in_bounds(&self, start: usize, len: usize) -> bool {
+        match start.checked_add(len) {
+            Some(end) => end <= self.buffer().len(),
+            None => false,
+        }
+    }
+
     fn get_data(&self, start: usize, len: usize) -> Result<&[u8]> {
-        let buffer = self.buffer();
-        let end = start.wrapping_add(len);
-        if end < start || end > buffer.len() {
-            Err(ErrorKind::BadMemDb.into())
-        } else {
-            Ok(&buffer[start..end])
+        if !self.is_in_bounds(start, len) {
+            return Err(ErrorKind::BadMemDb(format!(
+                "read of {} bytes at offset {} is out of bounds", len, start)).into());
         }
+        Ok(&self.buffer()[start..start + len])
     }
 
+    // the memdb types are all packed so they can be read from any
+    // position in the buffer once the bounds are checked.
     fn get_slice<T>(&self, offset: usize, count: usize) -> Result<&[T]> {
-        let size = mem::size_of::<T>();
-        Ok(unsafe {
-            slice::from_raw_parts(
-                mem::transmute(self.get_data(offset, count * size)?.as_ptr()),
-                count
-            )
-        })
+        let len = count.checked_mul(mem::size_of::<T>()).ok_or_else(|| {
+            Error::from(ErrorKind::BadMemDb(format!(
+                "table of {} items at offset {} is too large", count, offset)))
+        })?;
+        let data = self.get_data(offset, len)?;
+        Ok(unsafe { slice::from_raw_parts(data.as_ptr() as *const T, count) })
     }
 
     #[inline(always)]
-    fn header(&self) -> Result<&MemDbHeader> {
-        unsafe {
-            Ok(mem::transmute(self.get_data(0, mem::size_of::<MemDbHeader>())?.as_ptr()))
-        }
+    fn get_struct<T>(&self, offset: usize) -> Result<&T> {
+        let data = self.get_data(offset, mem::size_of::<T>())?;
+        Ok(unsafe { &*(data.as_ptr() as *const T) })
     }
 
     #[inline(always)]
@@ -137,20 +310,202 @@ impl<'a> Symbol<'a> {
     pub fn addr(&self) -> u64 {
         self.addr
     }
+
+    /// The size of the symbol if known
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// The offset of the looked up address into the symbol
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The kind of section the symbol is defined in
+    pub fn section_kind(&self) -> SectionKind {
+        self.section_kind
+    }
+}
+
+impl<'a> ObjectInfo<'a> {
+
+    /// The uuid of the image
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// The architecture of the image
+    pub fn arch(&self) -> &str {
+        self.arch
+    }
+
+    /// The preferred load address of the image
+    pub fn vmaddr(&self) -> u64 {
+        self.vmaddr
+    }
+
+    /// The size of the image in memory or 0 if unknown
+    pub fn vmsize(&self) -> u64 {
+        self.vmsize
+    }
+
+    /// The path of the image within the SDK
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Layout {
+
+    fn from_header(header: &MemDbHeader) -> Layout {
+        Layout {
+            version: header.version,
+            header_size: mem::size_of::<MemDbHeader>(),
+            checksum: Some(header.checksum),
+            variants_start: header.variants_start as usize,
+            variants_count: header.variants_count as usize,
+            uuids_start: header.uuids_start as usize,
+            uuids_count: header.uuids_count as usize,
+            tagged_object_names_start: header.tagged_object_names_start as usize,
+            tagged_object_names_end: header.tagged_object_names_end as usize,
+            object_names_start: header.object_names_start as usize,
+            object_names_count: header.object_names_count as usize,
+            symbols_start: header.symbols_start as usize,
+            symbols_count: header.symbols_count as usize,
+            object_name_index_start: header.object_name_index_start as usize,
+            object_name_index_count: header.object_name_index_count as usize,
+            symbol_name_index_start: header.symbol_name_index_start as usize,
+            symbol_name_index_count: header.symbol_name_index_count as usize,
+            variant_info_start: header.variant_info_start as usize,
+            variant_info_count: header.variant_info_count as usize,
+            symbol_groups_start: header.symbol_groups_start as usize,
+            symbol_groups_count: header.symbol_groups_count as usize,
+            symbol_restart_interval: header.symbol_restart_interval as usize,
+        }
+    }
+
+    fn from_header_v2(header: &MemDbHeaderV2) -> Layout {
+        Layout {
+            version: header.version,
+            header_size: mem::size_of::<MemDbHeaderV2>(),
+            checksum: None,
+            variants_start: header.variants_start as usize,
+            variants_count: header.variants_count as usize,
+            uuids_start: header.uuids_start as usize,
+            uuids_count: header.uuids_count as usize,
+            tagged_object_names_start: header.tagged_object_names_start as usize,
+            tagged_object_names_end: header.tagged_object_names_end as usize,
+            object_names_start: header.object_names_start as usize,
+            object_names_count: header.object_names_count as usize,
+            symbols_start: header.symbols_start as usize,
+            symbols_count: header.symbols_count as usize,
+            object_name_index_start: 0,
+            object_name_index_count: 0,
+            symbol_name_index_start: 0,
+            symbol_name_index_count: 0,
+            variant_info_start: 0,
+            variant_info_count: 0,
+            symbol_groups_start: 0,
+            symbol_groups_count: 0,
+            symbol_restart_interval: 0,
+        }
+    }
+}
+
+impl<'a> IndexView<'a> {
+
+    fn get(&self, idx: usize) -> Option<IndexItem> {
+        match *self {
+            IndexView::V2(items) => items.get(idx).map(|x| x.to_index_item()),
+            IndexView::V3(items) => items.get(idx).map(|x| *x),
+        }
+    }
+
+    /// Finds the position of the last item at or before the address.
+    fn find(&self, addr: u64) -> Option<usize> {
+        match *self {
+            IndexView::V2(items) => binsearch_pos_by_key(items, addr, |x| x.addr()),
+            IndexView::V3(items) => binsearch_pos_by_key(items, addr, |x| x.addr()),
+        }
+    }
+
+    /// Returns the size of the symbol at a position.
+    ///
+    /// Version 2 files do not store sizes so the size is assumed to reach
+    /// up to the next item in the index.
+    fn symbol_size(&self, pos: usize) -> Option<u64> {
+        let item = try_opt!(self.get(pos));
+        if item.size() > 0 {
+            return Some(item.size());
+        }
+        let mut next_pos = pos + 1;
+        while let Some(next) = self.get(next_pos) {
+            if next.addr() > item.addr() {
+                return Some(next.addr() - item.addr());
+            }
+            next_pos += 1;
+        }
+        None
+    }
+}
+
+impl<'a> UuidView<'a> {
+
+    /// Returns the UUID and the variant index at a position.
+    fn get(&self, idx: usize) -> Option<(&'a Uuid, usize)> {
+        match *self {
+            UuidView::V2(items) => items.get(idx).map(|x| (x.uuid(), x.idx())),
+            UuidView::V3(items) => items.get(idx).map(|x| (x.uuid(), x.idx())),
+        }
+    }
+
+    /// Finds the position of an exactly matching UUID.
+    fn find(&self, uuid: &Uuid) -> Option<usize> {
+        let pos = try_opt!(match *self {
+            UuidView::V2(items) => binsearch_pos_by_key(items, *uuid, |x| *x.uuid()),
+            UuidView::V3(items) => binsearch_pos_by_key(items, *uuid, |x| *x.uuid()),
+        });
+        match self.get(pos) {
+            Some((found, _)) if found == uuid => Some(pos),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> SliceView<'a> {
+
+    fn get(&self, idx: usize) -> Option<StoredSlice> {
+        match *self {
+            SliceView::V2(items) => items.get(idx).map(|x| x.to_stored_slice()),
+            SliceView::V3(items) => items.get(idx).map(|x| *x),
+        }
+    }
 }
 
 fn load_memdb<'a>(backing: Backing<'a>) -> Result<MemDb<'a>> {
-    let info = {
-        let header = backing.header()?;
-        if header.version != 2 {
+    // all versions start out with the version and the SDK info so we can
+    // use the smallest header to figure out the version.
+    let version = backing.get_struct::<MemDbHeaderV2>(0)?.version;
+    let (layout, info) = match version {
+        2 => {
+            let header: &MemDbHeaderV2 = backing.get_struct(0)?;
+            (Layout::from_header_v2(header), header.sdk_info.to_sdk_info())
+        }
+        MEMDB_VERSION => {
+            let header: &MemDbHeader = backing.get_struct(0)?;
+            (Layout::from_header(header), header.sdk_info.to_sdk_info())
+        }
+        _ => {
             return Err(ErrorKind::UnsupportedMemDbVersion.into());
         }
-        header.sdk_info.to_sdk_info()
     };
-    Ok(MemDb {
+    let rv = MemDb {
         backing: backing,
+        layout: layout,
         info: info,
-    })
+    };
+    rv.validate()?;
+    Ok(rv)
 }
 
 impl<'a> MemDb<'a> {
@@ -176,16 +531,95 @@ impl<'a> MemDb<'a> {
         load_memdb(Backing::Mmap(mmap))
     }
 
+    /// Constructs a memdb object from a file that might be XZ compressed.
+    ///
+    /// Compressed files (like the `.memdbz` files of `convert-sdk
+    /// --compress`) are decompressed into memory, all others are mmapped.
+    pub fn from_compressed_path<P: AsRef<Path>>(path: P) -> Result<MemDb<'a>> {
+        let mut f = File::open(path.as_ref())?;
+        let mut magic = [0u8; 6];
+        if f.read_exact(&mut magic).is_err() || &magic != XZ_MAGIC {
+            return MemDb::from_path(path);
+        }
+        f.seek(SeekFrom::Start(0))?;
+        let mut buffer = vec![];
+        XzDecoder::new(f).read_to_end(&mut buffer)?;
+        MemDb::from_vec(buffer)
+    }
+
     /// Return the SDK info.
     pub fn info(&self) -> &SdkInfo {
         &self.info
     }
 
+    /// Returns the SHA-256 checksum of the file contents.
+    ///
+    /// Version 2 files do not carry a checksum.
+    pub fn checksum(&self) -> Option<&[u8]> {
+        self.layout.checksum.as_ref().map(|x| &x[..])
+    }
+
+    /// Verifies the file contents against the stored checksum.
+    ///
+    /// This reads the entire file so it is not done automatically when the
+    /// file is opened.  Files without checksum always pass.
+    pub fn verify_checksum(&self) -> Result<()> {
+        if let Some(checksum) = self.checksum() {
+            let buffer = self.backing.buffer();
+            let payload = self.backing.get_data(
+                self.layout.header_size,
+                buffer.len().saturating_sub(self.layout.header_size))?;
+            if digest::digest(&digest::SHA256, payload).as_ref() != checksum {
+                return Err(ErrorKind::BadMemDb("checksum mismatch".into()).into());
+            }
+        }
+        Ok(())
+    }
+
     /// Finds a symbol by UUID and address.
     pub fn lookup_by_uuid(&'a self, uuid: &Uuid, addr: u64) -> Option<Symbol<'a>> {
         self.lookup_impl(uuid, addr).ok().and_then(|x| x)
     }
 
+    /// Finds the function without symbol that an address falls into.
+    ///
+    /// These are functions known from `LC_FUNCTION_STARTS` that have no
+    /// symbol and that `lookup_by_uuid` does not resolve.  Returns the start
+    /// address of the function and its size if known.
+    pub fn lookup_unnamed_function(&self, uuid: &Uuid, addr: u64)
+        -> Option<(u64, Option<u64>)>
+    {
+        let index = try_opt!(self.get_index(uuid).ok().and_then(|x| x));
+        let pos = try_opt!(index.find(addr));
+        let item = try_opt!(index.get(pos));
+        if !item.is_unnamed_function() {
+            return None;
+        }
+        let size = index.symbol_size(pos);
+        if let Some(size) = size {
+            if addr - item.addr() >= size {
+                return None;
+            }
+        }
+        Some((item.addr(), size))
+    }
+
+    /// Returns the functions without symbol of an image as address and
+    /// size (0 if unknown).
+    pub fn list_unnamed_functions(&self, uuid: &Uuid) -> Result<Vec<(u64, u64)>> {
+        let mut rv = vec![];
+        if let Some(index) = self.get_index(uuid)? {
+            let mut pos = 0;
+            while let Some(item) = index.get(pos) {
+                if item.is_unnamed_function() {
+                    rv.push((item.addr(), item.size()));
+                }
+                pos += 1;
+            }
+        }
+        Ok(rv)
+    }
+
     /// Finds a symbol by object name and architecture
     pub fn lookup_by_object_name(&'a self, object_name: &str, arch: &str, addr: u64)
         -> Option<Symbol<'a>>
@@ -199,14 +633,34 @@ impl<'a> MemDb<'a> {
 
     /// Given an object namd and architecture this finds the image UUID in the file.
     pub fn find_uuid(&self, object_name: &str, arch: &str) -> Result<Option<&Uuid>> {
-        let header = self.backing.header()?;
-        let mut offset = header.tagged_object_names_start as usize;
         let refstr = format!("{}:{}", object_name, arch);
+
+        // version 4 files come with a sorted name index
+        if self.layout.version >= 4 {
+            let names = self.object_name_index()?;
+            if let Some(item) = binsearch_by_key(names, refstr.as_bytes(), |item| {
+                self.backing.get_data(item.name().offset(), item.name().len())
+                    .unwrap_or(&b""[..])
+            }) {
+                let name = self.backing.get_data(item.name().offset(), item.name().len())?;
+                if name == refstr.as_bytes() {
+                    return match self.uuids()?.get(item.uuid_idx()) {
+                        Some((uuid, _)) => Ok(Some(uuid)),
+                        None => Err(ErrorKind::BadMemDb(
+                            "object name points to missing uuid".into()).into()),
+                    };
+                }
+            }
+            return Ok(None);
+        }
+
+        // older files require a linear scan over the tagged names
+        let mut offset = self.layout.tagged_object_names_start;
         let mut uuid_idx = 0;
-        while offset < header.tagged_object_names_end as usize {
+        while offset < self.layout.tagged_object_names_end {
             let s = self.get_cstr(offset)?;
             if s == &refstr {
-                return Ok(Some(&self.uuids()?[uuid_idx].uuid));
+                return Ok(self.uuids()?.get(uuid_idx).map(|(uuid, _)| uuid));
             }
             offset += s.len() + 1;
             uuid_idx += 1;
@@ -214,17 +668,174 @@ impl<'a> MemDb<'a> {
         Ok(None)
     }
 
+    /// Returns the stored metadata of the image with the given UUID.
+    ///
+    /// Older files do not store this metadata in which case `None` is
+    /// returned.
+    pub fn object_info(&'a self, uuid: &Uuid) -> Result<Option<ObjectInfo<'a>>> {
+        let uuids = self.uuids()?;
+        let variant_idx = match uuids.find(uuid).and_then(|pos| uuids.get(pos)) {
+            Some((_, idx)) => idx,
+            None => { return Ok(None); }
+        };
+        let info = match self.variant_infos()?.get(variant_idx) {
+            Some(info) => info,
+            None => { return Ok(None); }
+        };
+        Ok(Some(ObjectInfo {
+            uuid: *uuid,
+            arch: info.arch(),
+            vmaddr: info.vmaddr(),
+            vmsize: info.vmsize(),
+            path: self.get_object_name(info.src_id())?,
+        }))
+    }
+
+    /// Returns the UUIDs of all variants in the file.
+    pub fn list_uuids(&self) -> Result<Vec<&Uuid>> {
+        let uuids = self.uuids()?;
+        let mut rv = vec![];
+        let mut pos = 0;
+        while let Some((uuid, _)) = uuids.get(pos) {
+            rv.push(uuid);
+            pos += 1;
+        }
+        Ok(rv)
+    }
+
+    /// Returns all object names with their architecture and UUID.
+    pub fn list_object_names(&self) -> Result<Vec<(&str, &str, &Uuid)>> {
+        let uuids = self.uuids()?;
+        let mut tagged_names = vec![];
+
+        if self.layout.version >= 4 {
+            for item in self.object_name_index()?.iter() {
+                let name = self.backing.get_data(item.name().offset(), item.name().len())?;
+                tagged_names.push((from_utf8(name)?, item.uuid_idx()));
+            }
+        } else {
+            let mut offset = self.layout.tagged_object_names_start;
+            let mut uuid_idx = 0;
+            while offset < self.layout.tagged_object_names_end {
+                let s = self.get_cstr(offset)?;
+                tagged_names.push((s, uuid_idx));
+                offset += s.len() + 1;
+                uuid_idx += 1;
+            }
+        }
+
+        let mut rv = vec![];
+        for (tagged_name, uuid_idx) in tagged_names {
+            let mut parts = tagged_name.rsplitn(2, ':');
+            if_chain! {
+                if let Some(arch) = parts.next();
+                if let Some(name) = parts.next();
+                if let Some((uuid, _)) = uuids.get(uuid_idx);
+                then {
+                    rv.push((name, arch, uuid));
+                }
+            }
+        }
+        Ok(rv)
+    }
+
+    /// Collects size and count statistics about the file.
+    pub fn stats(&self) -> Result<MemDbStats> {
+        let layout = &self.layout;
+        let (slice_size, uuid_size) = if layout.version >= 4 {
+            (mem::size_of::<StoredSlice>(), mem::size_of::<IndexedUuid>())
+        } else {
+            (mem::size_of::<StoredSliceV2>(), mem::size_of::<IndexedUuidV2>())
+        };
+
+        let mut string_bytes = layout.tagged_object_names_end - layout.tagged_object_names_start;
+        let symbol_strings = if self.is_front_coded() {
+            self.symbol_groups()?
+        } else {
+            self.symbols()?
+        };
+        for strings in vec![symbol_strings, self.object_names()?] {
+            let mut idx = 0;
+            while let Some(slice) = strings.get(idx) {
+                string_bytes += slice.len();
+                idx += 1;
+            }
+        }
+
+        let mut index_bytes = layout.variants_count * slice_size +
+            layout.uuids_count * uuid_size +
+            layout.object_name_index_count * mem::size_of::<IndexedObjectName>() +
+            layout.symbol_name_index_count * mem::size_of::<IndexedSymbolName>() +
+            layout.variant_info_count * mem::size_of::<VariantInfo>();
+        let variants = self.variants()?;
+        let mut idx = 0;
+        while let Some(slice) = variants.get(idx) {
+            index_bytes += slice.len();
+            idx += 1;
+        }
+
+        let mut objects = vec![];
+        let mut unnamed_function_count = 0;
+        for (name, arch, uuid) in self.list_variants()? {
+            let mut symbol_count = 0;
+            if let Some(index) = self.get_index(uuid)? {
+                let mut pos = 0;
+                while let Some(item) = index.get(pos) {
+                    if item.sym_id().is_some() {
+                        symbol_count += 1;
+                    } else if item.is_unnamed_function() {
+                        unnamed_function_count += 1;
+                    }
+                    pos += 1;
+                }
+            }
+            objects.push((name.to_string(), arch.to_string(), *uuid, symbol_count));
+        }
+
+        Ok(MemDbStats {
+            version: layout.version,
+            file_size: self.backing.buffer().len(),
+            sections: vec![
+                ("variants", layout.variants_start, layout.variants_count),
+                ("uuids", layout.uuids_start, layout.uuids_count),
+                ("variant info", layout.variant_info_start, layout.variant_info_count),
+                ("tagged object names", layout.tagged_object_names_start,
+                 layout.tagged_object_names_end - layout.tagged_object_names_start),
+                ("object names", layout.object_names_start, layout.object_names_count),
+                ("symbols", layout.symbols_start, layout.symbols_count),
+                ("symbol groups", layout.symbol_groups_start, layout.symbol_groups_count),
+                ("object name index", layout.object_name_index_start,
+                 layout.object_name_index_count),
+                ("symbol name index", layout.symbol_name_index_start,
+                 layout.symbol_name_index_count),
+            ],
+            variant_count: layout.variants_count,
+            uuid_count: layout.uuids_count,
+            object_name_count: layout.object_names_count,
+            symbol_count: layout.symbols_count,
+            unnamed_function_count: unnamed_function_count,
+            string_bytes: string_bytes,
+            index_bytes: index_bytes,
+            objects: objects,
+        })
+    }
+
+    /// Returns every variant with the first object name it is known under.
+    pub fn list_variants(&self) -> Result<Vec<(&str, &str, &Uuid)>> {
+        let mut seen = HashSet::new();
+        Ok(self.list_object_names()?.into_iter()
+            .filter(|&(_, _, uuid)| seen.insert(uuid))
+            .collect())
+    }
+
     /// Given object name and architecture or UUID as string, this finds the
     /// UUID in the file.
     pub fn find_uuid_fuzzy(&self, name_or_uuid: &str) -> Result<Option<&Uuid>> {
         if let Ok(parsed_uuid) = name_or_uuid.parse::<Uuid>() {
             let uuids = self.uuids()?;
-            if let Some(item) = binsearch_by_key(uuids, parsed_uuid, |item| *item.uuid()) {
-                if item.uuid() == &parsed_uuid {
-                    return Ok(Some(item.uuid()));
-                }
-            }
-            return Ok(None)
+            return Ok(uuids.find(&parsed_uuid)
+                .and_then(|pos| uuids.get(pos))
+                .map(|(uuid, _)| uuid));
         }
         let mut parts = name_or_uuid.rsplitn(2, ':');
         if_chain! {
@@ -239,9 +850,108 @@ impl<'a> MemDb<'a> {
         }
     }
 
+    /// Finds all items with the given symbol name across all variants.
+    ///
+    /// Version 2 files have no symbol name index and need to scan all
+    /// variants which is slow.
+    pub fn find_symbol(&'a self, name: &str) -> Result<Vec<Symbol<'a>>> {
+        let mut rv = vec![];
+
+        if self.layout.version < 4 {
+            let uuids = self.uuids()?;
+            let mut uuid_idx = 0;
+            while let Some((uuid, _)) = uuids.get(uuid_idx) {
+                for sym in self.iter_symbols(uuid)? {
+                    let sym = sym?;
+                    if sym.symbol() == name {
+                        rv.push(sym);
+                    }
+                }
+                uuid_idx += 1;
+            }
+            return Ok(rv);
+        }
+
+        let index = self.symbol_name_index()?;
+        let low = self.symbol_name_lower_bound(name)?;
+        let uuids = self.uuids()?;
+        let variants = self.variants()?;
+        let mut sym_id = None;
+        for entry in index[low..].iter() {
+            // all entries for a name share the same symbol
+            if sym_id.is_none() {
+                if &*self.get_symbol(Some(entry.sym_id()))?.unwrap_or_default() != name {
+                    break;
+                }
+                sym_id = Some(entry.sym_id());
+            } else if sym_id != Some(entry.sym_id()) {
+                break;
+            }
+            let (uuid, variant_idx) = uuids.get(entry.uuid_idx()).ok_or_else(|| {
+                Error::from(ErrorKind::BadMemDb(
+                    "symbol name points to missing uuid".into()))
+            })?;
+            let variant_slice = variants.get(variant_idx).ok_or_else(|| {
+                Error::from(ErrorKind::BadMemDb(
+                    format!("uuid {} points to missing variant", uuid)))
+            })?;
+            let variant_index = self.get_index_view(&variant_slice)?;
+            let item = variant_index.get(entry.item_idx()).ok_or_else(|| {
+                Error::from(ErrorKind::BadMemDb(
+                    "symbol name points to missing item".into()))
+            })?;
+            let size = variant_index.symbol_size(entry.item_idx());
+            if let Some(sym) = self.index_item_to_symbol(&item, uuid, size, item.addr())? {
+                rv.push(sym);
+            }
+        }
+
+        Ok(rv)
+    }
+
+    /// Returns the position of the first entry in the symbol name index
+    /// whose symbol is not smaller than the given name.
+    fn symbol_name_lower_bound(&'a self, name: &str) -> Result<usize> {
+        let index = self.symbol_name_index()?;
+        let mut low = 0;
+        let mut high = index.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if &*self.get_symbol(Some(index[mid].sym_id()))?.unwrap_or_default() < name {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Searches the symbol strings of the file.
+    ///
+    /// Every distinct symbol string is yielded once.  Use `skip` and `take`
+    /// on the iterator to page through the results.  Prefix searches use
+    /// the symbol name index, regular expressions need to scan all symbols.
+    pub fn search_symbols(&'a self, pattern: SymbolPattern<'a>)
+        -> Result<SymbolSearchIter<'a>>
+    {
+        let (indexed, pos) = match pattern {
+            SymbolPattern::Prefix(prefix) if self.layout.version >= 4 => {
+                (true, self.symbol_name_lower_bound(prefix)?)
+            }
+            _ => (false, 0),
+        };
+        Ok(SymbolSearchIter {
+            memdb: self,
+            pattern: pattern,
+            pos: pos,
+            indexed: indexed,
+            last_sym_id: None,
+        })
+    }
+
     /// Returns the symbols for an Uuid
     pub fn iter_symbols(&'a self, uuid: &'a Uuid) -> Result<SymbolIter<'a>> {
-        let index = self.get_index(uuid)?.unwrap_or(&[][..]);
+        let index = self.get_index(uuid)?.unwrap_or(IndexView::V3(&[]));
         Ok(SymbolIter {
             memdb: self,
             uuid: uuid,
@@ -250,99 +960,386 @@ impl<'a> MemDb<'a> {
         })
     }
 
+    /// Reads a NUL terminated string from the tagged object names.
     fn get_cstr(&self, offset: usize) -> Result<&str> {
-        unsafe {
-            Ok(from_utf8(CStr::from_ptr(self.backing.buffer().as_ptr().offset(
-                offset as isize) as *const c_char).to_bytes())?)
+        let end = self.layout.tagged_object_names_end;
+        let data = self.backing.get_data(offset, end.saturating_sub(offset))?;
+        match data.iter().position(|&x| x == 0) {
+            Some(len) => Ok(from_utf8(&data[..len])?),
+            None => Err(ErrorKind::BadMemDb(format!(
+                "tagged object name at offset {} is not terminated", offset)).into()),
+        }
+    }
+
+    fn bad_memdb(&self, reason: String) -> Result<()> {
+        Err(ErrorKind::BadMemDb(reason).into())
+    }
+
+    fn check_table(&self, what: &str, start: usize, count: usize,
+                   item_size: usize) -> Result<()> {
+        match count.checked_mul(item_size) {
+            Some(len) if self.backing.is_in_bounds(start, len) => Ok(()),
+            _ => self.bad_memdb(format!(
+                "{} ({} items at offset {}) is out of bounds", what, count, start)),
+        }
+    }
+
+    fn check_strings(&self, what: &str, strings: &SliceView) -> Result<()> {
+        let mut idx = 0;
+        while let Some(slice) = strings.get(idx) {
+            if !self.backing.is_in_bounds(slice.offset(), slice.len()) {
+                return self.bad_memdb(format!("{} {} is out of bounds", what, idx));
+            }
+            idx += 1;
+        }
+        Ok(())
+    }
+
+    /// Checks the file for consistency.
+    ///
+    /// This makes sure that all ranges, slices and indexes in the file point
+    /// to valid data so that lookups can never read past the buffer.
+    fn validate(&self) -> Result<()> {
+        let layout = &self.layout;
+        let (slice_size, uuid_size, item_size) = if layout.version >= 4 {
+            (mem::size_of::<StoredSlice>(), mem::size_of::<IndexedUuid>(),
+             mem::size_of::<IndexItem>())
+        } else {
+            (mem::size_of::<StoredSliceV2>(), mem::size_of::<IndexedUuidV2>(),
+             mem::size_of::<IndexItemV2>())
+        };
+
+        // header ranges
+        self.check_table("variant table", layout.variants_start,
+                         layout.variants_count, slice_size)?;
+        self.check_table("uuid index", layout.uuids_start,
+                         layout.uuids_count, uuid_size)?;
+        self.check_table("object name table", layout.object_names_start,
+                         layout.object_names_count, slice_size)?;
+        self.check_table("object name index", layout.object_name_index_start,
+                         layout.object_name_index_count,
+                         mem::size_of::<IndexedObjectName>())?;
+        self.check_table("symbol name index", layout.symbol_name_index_start,
+                         layout.symbol_name_index_count,
+                         mem::size_of::<IndexedSymbolName>())?;
+        self.check_table("variant info table", layout.variant_info_start,
+                         layout.variant_info_count, mem::size_of::<VariantInfo>())?;
+        if layout.variant_info_count != 0 &&
+           layout.variant_info_count != layout.variants_count {
+            return self.bad_memdb("variant info table does not match variants".into());
         }
+        let names_start = layout.tagged_object_names_start;
+        let names_end = layout.tagged_object_names_end;
+        if names_end < names_start ||
+           !self.backing.is_in_bounds(names_start, names_end - names_start) {
+            return self.bad_memdb("tagged object names are out of bounds".into());
+        }
+        if names_end > names_start && self.backing.buffer()[names_end - 1] != 0 {
+            return self.bad_memdb("tagged object names are not terminated".into());
+        }
+
+        // strings.  Front-coded symbols are checked when they are decoded.
+        self.check_strings("object name", &self.object_names()?)?;
+        if self.is_front_coded() {
+            let interval = layout.symbol_restart_interval;
+            self.check_table("symbol group table", layout.symbol_groups_start,
+                             layout.symbol_groups_count, slice_size)?;
+            if layout.symbol_groups_count != layout.symbols_count / interval +
+               if layout.symbols_count % interval != 0 { 1 } else { 0 } {
+                return self.bad_memdb("symbol groups do not match symbols".into());
+            }
+            self.check_strings("symbol group", &self.symbol_groups()?)?;
+        } else {
+            self.check_table("symbol table", layout.symbols_start,
+                             layout.symbols_count, slice_size)?;
+            self.check_strings("symbol", &self.symbols()?)?;
+        }
+
+        // symbol indexes of all variants
+        let variants = self.variants()?;
+        let mut variant_idx = 0;
+        while let Some(slice) = variants.get(variant_idx) {
+            if !self.backing.is_in_bounds(slice.offset(), slice.len()) ||
+               slice.len() % item_size != 0 {
+                return self.bad_memdb(format!(
+                    "index of variant {} is out of bounds", variant_idx));
+            }
+            let index = self.get_index_view(&slice)?;
+            let mut last_addr = 0;
+            let mut pos = 0;
+            while let Some(item) = index.get(pos) {
+                if item.addr() < last_addr {
+                    return self.bad_memdb(format!(
+                        "index of variant {} is not sorted", variant_idx));
+                }
+                if item.src_id() as usize >= layout.object_names_count {
+                    return self.bad_memdb(format!(
+                        "item {} of variant {} points to missing object name",
+                        pos, variant_idx));
+                }
+                if let Some(sym_id) = item.sym_id() {
+                    if sym_id as usize >= layout.symbols_count {
+                        return self.bad_memdb(format!(
+                            "item {} of variant {} points to missing symbol",
+                            pos, variant_idx));
+                    }
+                }
+                last_addr = item.addr();
+                pos += 1;
+            }
+            variant_idx += 1;
+        }
+
+        // uuid index
+        let uuids = self.uuids()?;
+        let mut last_uuid = None;
+        let mut pos = 0;
+        while let Some((uuid, variant_idx)) = uuids.get(pos) {
+            if variant_idx >= layout.variants_count {
+                return self.bad_memdb(format!(
+                    "uuid {} points to missing variant", uuid));
+            }
+            if last_uuid.map_or(false, |last| last > uuid) {
+                return self.bad_memdb("uuid index is not sorted".into());
+            }
+            last_uuid = Some(uuid);
+            pos += 1;
+        }
+
+        // variant metadata
+        for (idx, info) in self.variant_infos()?.iter().enumerate() {
+            if info.src_id() as usize >= layout.object_names_count {
+                return self.bad_memdb(format!(
+                    "info of variant {} points to missing object name", idx));
+            }
+        }
+
+        // name + arch index.  Names need to be followed by their terminator
+        // within the tagged object names.
+        for (idx, item) in self.object_name_index()?.iter().enumerate() {
+            let name = item.name();
+            if name.offset() < names_start ||
+               name.offset().saturating_add(name.len()) >= names_end {
+                return self.bad_memdb(format!(
+                    "object name index entry {} is out of bounds", idx));
+            }
+            if self.backing.get_data(name.offset() + name.len(), 1)?[0] != 0 {
+                return self.bad_memdb(format!(
+                    "object name index entry {} is not terminated", idx));
+            }
+            if item.uuid_idx() >= layout.uuids_count {
+                return self.bad_memdb(format!(
+                    "object name index entry {} points to missing uuid", idx));
+            }
+        }
+
+        // symbol name index.  Item positions are checked on lookup as that
+        // would require resolving every variant here.
+        for (idx, item) in self.symbol_name_index()?.iter().enumerate() {
+            if item.sym_id() as usize >= layout.symbols_count {
+                return self.bad_memdb(format!(
+                    "symbol name index entry {} points to missing symbol", idx));
+            }
+            if item.uuid_idx() >= layout.uuids_count {
+                return self.bad_memdb(format!(
+                    "symbol name index entry {} points to missing uuid", idx));
+            }
+        }
+
+        Ok(())
     }
 
     fn lookup_impl(&'a self, uuid: &Uuid, addr: u64) -> Result<Option<Symbol<'a>>>
     {
         if let Some(index) = self.get_index(uuid)? {
-            if let Some(item) = binsearch_by_key(index, addr, |item| item.addr()) {
-                return Ok(self.index_item_to_symbol(item, uuid)?);
+            if let Some(pos) = index.find(addr) {
+                if let Some(item) = index.get(pos) {
+                    let size = index.symbol_size(pos);
+                    // addresses past the end of the symbol are in padding or
+                    // in a function we do not know about.
+                    if let Some(size) = size {
+                        if addr - item.addr() >= size {
+                            return Ok(None);
+                        }
+                    }
+                    return Ok(self.index_item_to_symbol(&item, uuid, size, addr)?);
+                }
             }
         }
         Ok(None)
     }
 
     #[inline(always)]
-    fn uuids(&self) -> Result<&[IndexedUuid]> {
-        let head = self.backing.header()?;
-        self.backing.get_slice(head.uuids_start as usize, head.uuids_count as usize)
+    fn get_slice_view(&self, start: usize, count: usize) -> Result<SliceView> {
+        Ok(if self.layout.version >= 4 {
+            SliceView::V3(self.backing.get_slice(start, count)?)
+        } else {
+            SliceView::V2(self.backing.get_slice(start, count)?)
+        })
     }
 
     #[inline(always)]
-    fn variants(&self) -> Result<&[StoredSlice]> {
-        let head = self.backing.header()?;
-        self.backing.get_slice(head.variants_start as usize, head.variants_count as usize)
+    fn uuids(&self) -> Result<UuidView> {
+        let start = self.layout.uuids_start;
+        let count = self.layout.uuids_count;
+        Ok(if self.layout.version >= 4 {
+            UuidView::V3(self.backing.get_slice(start, count)?)
+        } else {
+            UuidView::V2(self.backing.get_slice(start, count)?)
+        })
     }
 
     #[inline(always)]
-    fn get_index(&self, uuid: &Uuid) -> Result<Option<&[IndexItem]>> {
+    fn variants(&self) -> Result<SliceView> {
+        self.get_slice_view(self.layout.variants_start, self.layout.variants_count)
+    }
+
+    #[inline(always)]
+    fn get_index(&self, uuid: &Uuid) -> Result<Option<IndexView>> {
         let uuids = self.uuids()?;
-        if let Some(iuuid) = binsearch_by_key(uuids, *uuid, |item| *item.uuid()) {
-            // only consider exact matches
-            if iuuid.uuid() != uuid {
-                return Ok(None);
-            }
-            let variant_slice = &self.variants()?[iuuid.idx()];
-            unsafe {
-                let data = self.backing.get_data(variant_slice.offset(),
-                                                 variant_slice.len())?;
-                let count = variant_slice.len() / mem::size_of::<IndexItem>();
-                return Ok(Some(slice::from_raw_parts(
-                    mem::transmute(data.as_ptr()),
-                    count
-                )));
-            }
-        }
-        Ok(None)
+        let variant_idx = match uuids.find(uuid).and_then(|pos| uuids.get(pos)) {
+            Some((_, idx)) => idx,
+            None => { return Ok(None); }
+        };
+        let variant_slice = self.variants()?.get(variant_idx).ok_or_else(|| {
+            Error::from(ErrorKind::BadMemDb(
+                format!("uuid {} points to missing variant", uuid)))
+        })?;
+        Ok(Some(self.get_index_view(&variant_slice)?))
+    }
+
+    #[inline(always)]
+    fn get_index_view(&self, variant_slice: &StoredSlice) -> Result<IndexView> {
+        let offset = variant_slice.offset();
+        let len = variant_slice.len();
+        Ok(if self.layout.version >= 4 {
+            IndexView::V3(self.backing.get_slice(
+                offset, len / mem::size_of::<IndexItem>())?)
+        } else {
+            IndexView::V2(self.backing.get_slice(
+                offset, len / mem::size_of::<IndexItemV2>())?)
+        })
+    }
+
+    #[inline(always)]
+    fn object_name_index(&self) -> Result<&[IndexedObjectName]> {
+        self.backing.get_slice(self.layout.object_name_index_start,
+                               self.layout.object_name_index_count)
+    }
+
+    #[inline(always)]
+    fn symbol_name_index(&self) -> Result<&[IndexedSymbolName]> {
+        self.backing.get_slice(self.layout.symbol_name_index_start,
+                               self.layout.symbol_name_index_count)
+    }
+
+    #[inline(always)]
+    fn variant_infos(&self) -> Result<&[VariantInfo]> {
+        self.backing.get_slice(self.layout.variant_info_start,
+                               self.layout.variant_info_count)
     }
 
     #[inline(always)]
-    fn symbols(&self) -> Result<&[StoredSlice]> {
-        let head = self.backing.header()?;
-        self.backing.get_slice(head.symbols_start as usize, head.symbols_count as usize)
+    fn symbols(&self) -> Result<SliceView> {
+        self.get_slice_view(self.layout.symbols_start, self.layout.symbols_count)
     }
 
     #[inline(always)]
-    fn object_names(&self) -> Result<&[StoredSlice]> {
-        let head = self.backing.header()?;
-        self.backing.get_slice(head.object_names_start as usize,
-                               head.object_names_count as usize)
+    fn symbol_groups(&self) -> Result<SliceView> {
+        self.get_slice_view(self.layout.symbol_groups_start,
+                            self.layout.symbol_groups_count)
+    }
+
+    #[inline(always)]
+    fn is_front_coded(&self) -> bool {
+        self.layout.symbol_restart_interval > 0
+    }
+
+    #[inline(always)]
+    fn object_names(&self) -> Result<SliceView> {
+        self.get_slice_view(self.layout.object_names_start,
+                            self.layout.object_names_count)
     }
 
     #[inline(always)]
     fn get_string(&'a self, slice: &StoredSlice) -> Result<Cow<'a, str>> {
         let bytes = self.backing.get_data(slice.offset(), slice.len())?;
         if slice.is_compressed() {
-            panic!("We do not support compression");
+            let mut rv = String::new();
+            BrotliDecoder::new(bytes).read_to_string(&mut rv)?;
+            Ok(Cow::Owned(rv))
         } else {
             Ok(Cow::Borrowed(from_utf8(bytes)?))
         }
     }
 
-    fn get_object_name(&'a self, src_id: u16) -> Result<Cow<'a, str>> {
-        self.get_string(&self.object_names()?[src_id as usize])
+    fn get_object_name(&'a self, src_id: u32) -> Result<Cow<'a, str>> {
+        match self.object_names()?.get(src_id as usize) {
+            Some(slice) => self.get_string(&slice),
+            None => Err(ErrorKind::BadMemDb(
+                format!("missing object name {}", src_id)).into()),
+        }
     }
 
     fn get_symbol(&'a self, sym_id: Option<u32>) -> Result<Option<Cow<'a, str>>> {
         if let Some(sym_id) = sym_id {
-            Ok(Some(self.get_string(&self.symbols()?[sym_id as usize])?))
+            Ok(Some(self.get_symbol_string(sym_id)?))
         } else {
             Ok(None)
         }
     }
 
-    fn index_item_to_symbol(&'a self, ii: &IndexItem, uuid: &Uuid) -> Result<Option<Symbol<'a>>> {
+    fn get_symbol_string(&'a self, sym_id: u32) -> Result<Cow<'a, str>> {
+        if self.is_front_coded() {
+            return self.get_front_coded_symbol(sym_id);
+        }
+        match self.symbols()?.get(sym_id as usize) {
+            Some(slice) => self.get_string(&slice),
+            None => Err(ErrorKind::BadMemDb(
+                format!("missing symbol {}", sym_id)).into()),
+        }
+    }
+
+    /// Decodes a symbol from its group up to its position in the group.
+    fn get_front_coded_symbol(&self, sym_id: u32) -> Result<Cow<'a, str>> {
+        let interval = self.layout.symbol_restart_interval;
+        let group_idx = sym_id as usize / interval;
+        let group = self.symbol_groups()?.get(group_idx).ok_or_else(|| {
+            Error::from(ErrorKind::BadMemDb(format!("missing symbol {}", sym_id)))
+        })?;
+        let data = self.backing.get_data(group.offset(), group.len())?;
+        let mut offset = 0;
+        let mut buf = vec![];
+        for _ in 0..sym_id as usize % interval + 1 {
+            let shared = read_uleb128(data, &mut offset);
+            let len = read_uleb128(data, &mut offset);
+            match (shared, len) {
+                (Some(shared), Some(len)) if shared as usize <= buf.len() &&
+                                             len as usize <= data.len() - offset => {
+                    buf.truncate(shared as usize);
+                    buf.extend_from_slice(&data[offset..offset + len as usize]);
+                    offset += len as usize;
+                }
+                _ => {
+                    return Err(ErrorKind::BadMemDb(
+                        format!("symbol group {} is corrupted", group_idx)).into());
+                }
+            }
+        }
+        Ok(Cow::Owned(String::from_utf8(buf)?))
+    }
+
+    fn index_item_to_symbol(&'a self, ii: &IndexItem, uuid: &Uuid, size: Option<u64>,
+                            lookup_addr: u64) -> Result<Option<Symbol<'a>>> {
         if let Some(symbol) = self.get_symbol(ii.sym_id())? {
             Ok(Some(Symbol {
                 object_uuid: uuid.clone(),
                 object_name: self.get_object_name(ii.src_id())?,
                 symbol: symbol,
                 addr: ii.addr(),
+                size: size,
+                offset: lookup_addr - ii.addr(),
+                section_kind: ii.section_kind(),
             }))
         } else {
             Ok(None)
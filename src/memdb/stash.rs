@@ -235,11 +235,27 @@ impl MemDbStash {
         let started = UTC::now();
         println!("{} {}", style("Updating").green(), sdk.info());
         let mut src = self.s3.download_sdk(sdk)?;
-        let dst = fs::File::create(self.path.join(sdk.info().memdb_filename()))?;
-        let mut dst = XzDecoder::new(dst);
-        copy_with_progress(&progress, &mut src, &mut dst)?;
+
+        // decompress into a temporary file first and only move it into
+        // place once the checksum matched.
+        let dst_path = self.path.join(sdk.info().memdb_filename());
+        let mut tmp_path = dst_path.clone();
+        tmp_path.set_extension("download");
+        {
+            let dst = fs::File::create(&tmp_path)?;
+            let mut dst = XzDecoder::new(dst);
+            copy_with_progress(&progress, &mut src, &mut dst)?;
+        }
         progress.finish_and_clear();
 
+        if let Err(err) = MemDb::from_path(&tmp_path).and_then(|memdb| {
+            memdb.verify_checksum()
+        }) {
+            fs::remove_file(&tmp_path).ok();
+            return Err(err).chain_err(|| format!("refusing corrupt memdb for {}", sdk.info()));
+        }
+        fs::rename(&tmp_path, &dst_path)?;
+
         let duration = UTC::now() - started;
         if !options.user_facing {
             info!("updated {} in {}", sdk.info(), HumanDuration(duration));
@@ -427,6 +443,17 @@ impl MemDbStash {
         Err(ErrorKind::UnknownSdk.into())
     }
 
+    /// Opens the memdb of an SDK, validates it and verifies its checksum.
+    ///
+    /// Returns `false` if the file carries no checksum (version 2 files)
+    /// and could only be validated.  Unlike `get_memdb` this always reads
+    /// the file from disk and does not cache the result.
+    pub fn verify_memdb(&self, info: &SdkInfo) -> Result<bool> {
+        let memdb = MemDb::from_path(self.path.join(&info.memdb_filename()))?;
+        memdb.verify_checksum()?;
+        Ok(memdb.checksum().is_some())
+    }
+
     /// Looks up an memdb by an SDK info as string if available.
     pub fn get_memdb_from_sdk_id(&self, sdk_id: &str) -> Result<Arc<MemDb<'static>>> {
         if let Some(sdk_info) = SdkInfo::from_filename(sdk_id) {
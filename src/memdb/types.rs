@@ -4,12 +4,54 @@ use std::str::from_utf8;
 use uuid::Uuid;
 
 use super::super::sdk::SdkInfo;
+use super::super::dsym::SectionKind;
 
 
+/// The memdb format version written by this library
+///
+/// Version 3 was only ever written by development builds while its layout
+/// was still changing.  Such files cannot be told apart and are rejected.
+pub const MEMDB_VERSION: u32 = 4;
+
+/// The symbol id of index items that start a function without symbol
+const UNNAMED_FUNCTION_SYM_ID: u32 = !1;
+
 /// The stored memdb file header
+///
+/// The checksum is the SHA-256 of everything following the header.  If
+/// `symbol_restart_interval` is not 0 the symbols are not stored as table
+/// of slices but front-coded in groups (see `symbol_groups_start`).
 #[repr(C, packed)]
 #[derive(Default, Copy, Clone)]
 pub struct MemDbHeader {
+    pub version: u32,
+    pub sdk_info: PackedSdkInfo,
+    pub variants_start: u64,
+    pub variants_count: u64,
+    pub uuids_start: u64,
+    pub uuids_count: u64,
+    pub tagged_object_names_start: u64,
+    pub tagged_object_names_end: u64,
+    pub object_names_start: u64,
+    pub object_names_count: u64,
+    pub symbols_start: u64,
+    pub symbols_count: u64,
+    pub object_name_index_start: u64,
+    pub object_name_index_count: u64,
+    pub symbol_name_index_start: u64,
+    pub symbol_name_index_count: u64,
+    pub variant_info_start: u64,
+    pub variant_info_count: u64,
+    pub symbol_groups_start: u64,
+    pub symbol_groups_count: u64,
+    pub symbol_restart_interval: u64,
+    pub checksum: [u8; 32],
+}
+
+/// The stored memdb file header of version 2 files
+#[repr(C, packed)]
+#[derive(Default, Copy, Clone)]
+pub struct MemDbHeaderV2 {
     pub version: u32,
     pub sdk_info: PackedSdkInfo,
     pub variants_start: u32,
@@ -37,7 +79,16 @@ pub struct PackedSdkInfo {
 
 /// A stored slice that points to a memory region in the memdb file
 #[repr(C, packed)]
+#[derive(Copy, Clone)]
 pub struct StoredSlice {
+    pub offset: u64,
+    pub len: u32,
+}
+
+/// A stored slice in version 2 files
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct StoredSliceV2 {
     pub offset: u32,
     pub len: u32,
 }
@@ -45,14 +96,64 @@ pub struct StoredSlice {
 /// For the UUID index this points to a variant by index
 #[repr(C, packed)]
 pub struct IndexedUuid {
+    pub uuid: Uuid,
+    pub idx: u32,
+}
+
+/// An entry in the UUID index of version 2 files
+#[repr(C, packed)]
+pub struct IndexedUuidV2 {
     pub uuid: Uuid,
     pub idx: u16,
 }
 
+/// Maps a tagged object name (`name:arch`) to an entry in the UUID index
+///
+/// These are stored sorted by name so that they can be binary searched.
+#[repr(C, packed)]
+pub struct IndexedObjectName {
+    pub name: StoredSlice,
+    pub uuid_idx: u32,
+}
+
+/// Points from a symbol name to one of its items in a variant's index
+///
+/// These are stored sorted by symbol name so that they can be binary
+/// searched.
+#[repr(C, packed)]
+pub struct IndexedSymbolName {
+    pub sym_id: u32,
+    pub uuid_idx: u32,
+    pub item_idx: u32,
+}
+
+/// Metadata of a variant
+///
+/// These are stored in the same order as the variants.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct VariantInfo {
+    vmaddr: u64,
+    vmsize: u64,
+    src_id: u32,
+    arch: [u8; 16],
+}
+
 /// A symbol in the index
 #[repr(C, packed)]
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct IndexItem {
+    addr: u64,
+    size: u32,
+    src_id: u32,
+    sym_id: u32,
+    kind: u8,
+}
+
+/// A symbol in the index of version 2 files
+#[repr(C, packed)]
+#[derive(Debug, Copy, Clone)]
+pub struct IndexItemV2 {
     addr_low: u32,
     addr_high: u16,
     src_id: u16,
@@ -92,12 +193,56 @@ impl PackedSdkInfo {
     }
 }
 
+impl VariantInfo {
+
+    /// Creates new variant info.  Architectures that do not fit are
+    /// stored as empty string.
+    pub fn new(vmaddr: u64, vmsize: u64, src_id: u32, arch: &str) -> VariantInfo {
+        let mut rv = VariantInfo {
+            vmaddr: vmaddr,
+            vmsize: vmsize,
+            src_id: src_id,
+            arch: [0; 16],
+        };
+        if arch.len() <= rv.arch.len() {
+            copy_str_to_slice(&mut rv.arch[..], arch);
+        }
+        rv
+    }
+
+    /// The address of the `__TEXT` segment, 0 if unknown.
+    pub fn vmaddr(&self) -> u64 {
+        self.vmaddr
+    }
+
+    /// The size of the `__TEXT` segment, 0 if unknown.
+    pub fn vmsize(&self) -> u64 {
+        self.vmsize
+    }
+
+    /// The id of the object name (install path).
+    pub fn src_id(&self) -> u32 {
+        self.src_id
+    }
+
+    /// The architecture name.
+    pub fn arch(&self) -> &str {
+        let len = self.arch.iter().position(|&x| x == 0).unwrap_or(self.arch.len());
+        from_utf8(&self.arch[..len]).unwrap_or("")
+    }
+
+    /// Returns a copy with a different object name id.
+    pub fn with_src_id(&self, src_id: u32) -> VariantInfo {
+        VariantInfo { src_id: src_id, ..*self }
+    }
+}
+
 impl IndexedUuid {
 
     pub fn new(uuid: &Uuid, idx: usize) -> IndexedUuid {
         IndexedUuid {
             uuid: *uuid,
-            idx: idx as u16,
+            idx: idx as u32,
         }
     }
 
@@ -110,6 +255,58 @@ impl IndexedUuid {
     }
 }
 
+impl IndexedUuidV2 {
+
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    pub fn idx(&self) -> usize {
+        self.idx as usize
+    }
+}
+
+impl IndexedObjectName {
+
+    pub fn new(name: StoredSlice, uuid_idx: usize) -> IndexedObjectName {
+        IndexedObjectName {
+            name: name,
+            uuid_idx: uuid_idx as u32,
+        }
+    }
+
+    pub fn name(&self) -> &StoredSlice {
+        &self.name
+    }
+
+    pub fn uuid_idx(&self) -> usize {
+        self.uuid_idx as usize
+    }
+}
+
+impl IndexedSymbolName {
+
+    pub fn new(sym_id: u32, uuid_idx: usize, item_idx: usize) -> IndexedSymbolName {
+        IndexedSymbolName {
+            sym_id: sym_id,
+            uuid_idx: uuid_idx as u32,
+            item_idx: item_idx as u32,
+        }
+    }
+
+    pub fn sym_id(&self) -> u32 {
+        self.sym_id
+    }
+
+    pub fn uuid_idx(&self) -> usize {
+        self.uuid_idx as usize
+    }
+
+    pub fn item_idx(&self) -> usize {
+        self.item_idx as usize
+    }
+}
+
 impl StoredSlice {
 
     /// Creates a new stored slice
@@ -118,7 +315,7 @@ impl StoredSlice {
             len |= 0x80000000;
         }
         StoredSlice {
-            offset: offset as u32,
+            offset: offset as u64,
             len: len as u32,
         }
     }
@@ -133,39 +330,105 @@ impl StoredSlice {
         (self.len as usize) & 0x7fffffff
     }
 
-    /// Indicates that the string is compressed (currently not used)
+    /// Indicates that the string is brotli compressed
     pub fn is_compressed(&self) -> bool {
         self.len >> 31 != 0
     }
 }
 
+impl StoredSliceV2 {
+
+    /// Upgrades the slice to the current format
+    pub fn to_stored_slice(&self) -> StoredSlice {
+        StoredSlice {
+            offset: self.offset as u64,
+            len: self.len,
+        }
+    }
+}
+
 impl IndexItem {
     /// Creates a new indexed symbol in the index
-    pub fn new(addr: u64, src_id: u16, sym_id: Option<u32>) -> IndexItem {
+    ///
+    /// A size of zero means that the size is unknown.
+    pub fn new(addr: u64, size: u64, src_id: u32, sym_id: Option<u32>,
+               kind: SectionKind) -> IndexItem {
         IndexItem {
-            addr_low: (addr & 0xffffffff) as u32,
-            addr_high: ((addr >> 32) &0xffff) as u16,
+            addr: addr,
+            size: if size > 0xffffffff { 0xffffffff } else { size as u32 },
             src_id: src_id,
             sym_id: sym_id.unwrap_or(!0),
+            kind: kind as u8,
+        }
+    }
+
+    /// Creates a boundary for a function that has no symbol
+    pub fn new_unnamed_function(addr: u64, size: u64, src_id: u32) -> IndexItem {
+        IndexItem {
+            sym_id: UNNAMED_FUNCTION_SYM_ID,
+            ..IndexItem::new(addr, size, src_id, None, SectionKind::Text)
+        }
+    }
+
+    /// Returns a copy with different ids.  Items without symbol keep
+    /// what they mark.
+    pub fn with_ids(&self, src_id: u32, sym_id: Option<u32>) -> IndexItem {
+        IndexItem {
+            src_id: src_id,
+            sym_id: sym_id.unwrap_or(self.sym_id),
+            ..*self
         }
     }
 
     /// The address of the symbol
     pub fn addr(&self) -> u64 {
-        ((self.addr_high as u64) << 32) | (self.addr_low as u64)
+        self.addr
+    }
+
+    /// The size of the symbol (0 if unknown)
+    pub fn size(&self) -> u64 {
+        self.size as u64
     }
 
     /// The ID of the source variant
-    pub fn src_id(&self) -> u16 {
+    pub fn src_id(&self) -> u32 {
         self.src_id
     }
 
     /// The ID of the symbol
     pub fn sym_id(&self) -> Option<u32> {
-        if self.sym_id == !0 {
+        if self.sym_id == !0 || self.sym_id == UNNAMED_FUNCTION_SYM_ID {
             None
         } else {
             Some(self.sym_id)
         }
     }
+
+    /// Indicates that this is the start of a function without symbol
+    pub fn is_unnamed_function(&self) -> bool {
+        self.sym_id == UNNAMED_FUNCTION_SYM_ID
+    }
+
+    /// The kind of section the symbol is defined in
+    pub fn section_kind(&self) -> SectionKind {
+        SectionKind::from_u8(self.kind)
+    }
+}
+
+impl IndexItemV2 {
+    /// The address of the symbol
+    pub fn addr(&self) -> u64 {
+        ((self.addr_high as u64) << 32) | (self.addr_low as u64)
+    }
+
+    /// Upgrades the item to the current format
+    pub fn to_index_item(&self) -> IndexItem {
+        IndexItem {
+            addr: self.addr(),
+            size: 0,
+            src_id: self.src_id as u32,
+            sym_id: self.sym_id,
+            kind: SectionKind::Text as u8,
+        }
+    }
 }
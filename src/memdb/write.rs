@@ -5,36 +5,78 @@
 //! that gets mmaped into the process.
 use std::io::{Write, Seek, SeekFrom};
 use std::fs::File;
+use std::path::PathBuf;
 use std::mem;
 use std::slice;
 use std::cell::RefCell;
-use std::collections::{HashSet, HashMap};
+use std::collections::{HashSet, HashMap, BTreeMap};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use std::thread;
 
 use uuid::Uuid;
+use num_cpus;
 use xz2::write::XzEncoder;
+use brotli2::write::BrotliEncoder;
+use ring::digest;
 use tempfile::tempfile;
 use console::{style, StyledObject};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use super::types::{IndexItem, StoredSlice, MemDbHeader, IndexedUuid};
-use super::super::Result;
-use super::super::sdk::{SdkInfo, DumpOptions, Objects};
-use super::super::dsym::{Object, Variant};
-use super::super::utils::{file_size_format, copy_with_progress};
+use super::types::{IndexItem, StoredSlice, MemDbHeader, IndexedUuid,
+                   IndexedObjectName, IndexedSymbolName, VariantInfo, MEMDB_VERSION};
+use super::super::{Result, Error};
+use super::super::sdk::{SdkInfo, DumpOptions, Objects, ObjectSource};
+use super::super::dsym::{Object, SectionKind};
+use super::read::MemDb;
+use super::super::breakpad::SymFile;
+use super::super::utils::{file_size_format, copy_with_progress, write_uleb128};
+
+
+/// Strings shorter than this are never compressed as brotli would not
+/// be able to make them any smaller.
+const COMPRESSION_THRESHOLD: usize = 48;
+
+/// How many front-coded symbols share a group.  Only the first symbol of a
+/// group is stored in full.
+const SYMBOL_RESTART_INTERVAL: usize = 16;
+
+/// A variant of an object with its symbols extracted
+///
+/// Symbols are `(addr, size, kind, name)` tuples as they go into the index.
+/// Functions without a symbol are `(addr, size)` tuples.
+struct ExtractedVariant {
+    uuid: Uuid,
+    src: String,
+    arch: String,
+    symbols: Vec<(u64, u64, SectionKind, String)>,
+    unnamed_functions: Vec<(u64, u64)>,
+    vmaddr: u64,
+    vmsize: u64,
+}
+
+/// A job for a worker: the sequence number, the file offset for the
+/// progress bar, the filename, the object to parse and the UUIDs of the
+/// variants to skip.
+type WorkerJob = (usize, usize, String, ObjectSource, Vec<Uuid>);
 
+/// The result of a worker for a job.
+type WorkerResult = (usize, usize, String, Result<Vec<ExtractedVariant>>);
 
 struct MemDbBuilder<W> {
     writer: RefCell<W>,
     tempfile: Option<RefCell<File>>,
+    checksum: RefCell<digest::Context>,
     info: SdkInfo,
     symbols: Vec<String>,
     symbols_map: HashMap<String, u32>,
     object_names: Vec<String>,
-    object_names_map: HashMap<String, u16>,
+    object_names_map: HashMap<String, u32>,
     object_uuid_mapping: Vec<(String, Uuid)>,
     variant_uuids: Vec<IndexedUuid>,
     variant_uuids_seen: HashSet<Uuid>,
     variants: Vec<Vec<IndexItem>>,
+    variant_infos: Vec<VariantInfo>,
     symbol_count: usize,
     options: DumpOptions,
 }
@@ -48,6 +90,182 @@ fn format_step(step: usize, opts: &DumpOptions) -> StyledObject<String> {
     style(format!("[{}/{}]", step, steps)).dim()
 }
 
+/// Compresses a string if it is long enough and compression actually
+/// makes it smaller.
+fn compress_string(s: &str) -> Result<Option<Vec<u8>>> {
+    if s.len() < COMPRESSION_THRESHOLD {
+        return Ok(None);
+    }
+    let mut encoder = BrotliEncoder::new(vec![], 9);
+    encoder.write_all(s.as_bytes())?;
+    let bytes = encoder.finish()?;
+    if bytes.len() < s.len() {
+        Ok(Some(bytes))
+    } else {
+        Ok(None)
+    }
+}
+
+fn as_bytes<T>(x: &T) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(x as *const T as *const u8, mem::size_of_val(x))
+    }
+}
+
+/// Extracts the symbols of all variants of an object.
+///
+/// Only symbols in the given sections are extracted.  Variants with a
+/// UUID in `skip` are left out.
+fn extract_object(obj: &Object, filename: &str, sections: &[String], skip: &[Uuid])
+    -> Result<Vec<ExtractedVariant>>
+{
+    let mut rv = vec![];
+    for var in obj.variants() {
+        let uuid = match var.uuid() {
+            Some(uuid) => uuid,
+            None => { continue; }
+        };
+        if skip.contains(&uuid) {
+            continue;
+        }
+        let mut symbols = obj.symbols(var.arch())?;
+
+        // function starts and symbols both mark the beginning of a
+        // function.  The size of a symbol reaches up to the next one.
+        let function_starts = symbols.function_starts();
+        let exports = symbols.exports(sections);
+        let objc_methods = symbols.objc_methods(sections);
+        let segment_ends = symbols.segment_ends(sections);
+        let mut boundaries = function_starts.clone();
+        let mut entries = vec![];
+        let mut iter = symbols.iter_sections(sections);
+        while let Some((addr, sym, kind)) = iter.next_with_kind() {
+            // symbols below the image cannot be stored
+            if let Some(addr) = addr.checked_sub(var.vmaddr()) {
+                entries.push((addr, sym.to_string(), kind));
+            }
+        }
+
+        // exported names and objc methods fill in for stripped symbols.
+        // The symbol table wins if several know an address.
+        let mut named: HashSet<_> = entries.iter().map(|&(addr, _, _)| addr).collect();
+        for (addr, sym, kind) in exports.into_iter().chain(objc_methods) {
+            if named.insert(addr) {
+                entries.push((addr, sym, kind));
+            }
+        }
+        // symbols end at the latest with the end of their segment
+        boundaries.extend(entries.iter().map(|&(addr, _, _)| addr));
+        boundaries.extend(segment_ends);
+        if var.vmsize() > 0 {
+            boundaries.push(var.vmsize());
+        }
+        boundaries.sort();
+        boundaries.dedup();
+        let size_at = |addr: u64| {
+            let next = match boundaries.binary_search(&addr) {
+                Ok(pos) => pos + 1,
+                Err(pos) => pos,
+            };
+            boundaries.get(next).map_or(0, |&end| end - addr)
+        };
+
+        rv.push(ExtractedVariant {
+            uuid: uuid,
+            src: var.name().unwrap_or(filename).to_string(),
+            arch: var.arch().to_string(),
+            symbols: entries.into_iter().map(|(addr, sym, kind)| {
+                (addr, size_at(addr), kind, sym)
+            }).collect(),
+            // function starts without a symbol are kept so that lookups do
+            // not run into the preceding symbol.
+            unnamed_functions: function_starts.iter()
+                .filter(|&&addr| !named.contains(&addr))
+                .map(|&addr| (addr, size_at(addr)))
+                .collect(),
+            vmaddr: var.vmaddr(),
+            vmsize: var.vmsize(),
+        });
+    }
+    Ok(rv)
+}
+
+/// Extracts a variant from an existing memdb file.
+///
+/// The given object name and architecture are used if the file does not
+/// store variant metadata.  The sizes of the symbols are carried over.
+fn extract_memdb_variant(memdb: &MemDb, src: &str, arch: &str, uuid: &Uuid)
+    -> Result<ExtractedVariant>
+{
+    let mut symbols = vec![];
+    for sym_rv in memdb.iter_symbols(uuid)? {
+        let sym = sym_rv?;
+        symbols.push((sym.addr(), sym.size().unwrap_or(0), sym.section_kind(),
+                      sym.symbol().to_string()));
+    }
+    let info = memdb.object_info(uuid)?;
+    Ok(ExtractedVariant {
+        uuid: *uuid,
+        src: info.as_ref().map_or(src, |x| x.path()).to_string(),
+        arch: info.as_ref().map_or(arch, |x| x.arch()).to_string(),
+        symbols: symbols,
+        unnamed_functions: memdb.list_unnamed_functions(uuid)?,
+        vmaddr: info.as_ref().map_or(0, |x| x.vmaddr()),
+        vmsize: info.as_ref().map_or(0, |x| x.vmsize()),
+    })
+}
+
+/// Parses and extracts objects on a pool of worker threads.
+///
+/// Results are sent back tagged with the sequence number of the job so
+/// that they can be put back into order.
+fn spawn_workers(threads: usize, sections: &[String])
+    -> (SyncSender<WorkerJob>, Receiver<WorkerResult>)
+{
+    let (job_tx, job_rx) = sync_channel::<WorkerJob>(threads * 2);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (result_tx, result_rx) = channel();
+
+    for _ in 0..threads {
+        let job_rx = job_rx.clone();
+        let result_tx = result_tx.clone();
+        let sections = sections.to_vec();
+        thread::spawn(move || {
+            loop {
+                let job = job_rx.lock().unwrap().recv();
+                let (seq, offset, filename, source, skip) = match job {
+                    Ok(job) => job,
+                    Err(_) => { break; }
+                };
+                let rv = source.parse().and_then(|obj| match obj {
+                    Some(obj) => extract_object(&obj, &filename, &sections, &skip),
+                    None => Ok(vec![]),
+                });
+                if result_tx.send((seq, offset, filename, rv)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    (job_tx, result_rx)
+}
+
+/// Sorts strings and returns a table that maps old to new positions.
+fn sort_strings(strings: &mut Vec<String>) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..strings.len()).collect();
+    order.sort_by(|&a, &b| strings[a].cmp(&strings[b]));
+    let mut ids = vec![0; strings.len()];
+    for (new_id, &old_id) in order.iter().enumerate() {
+        ids[old_id] = new_id as u32;
+    }
+    let sorted = order.iter()
+        .map(|&idx| mem::replace(&mut strings[idx], String::new()))
+        .collect();
+    *strings = sorted;
+    ids
+}
+
 trait WriteSeek : Write + Seek {}
 impl<T: Write+Seek> WriteSeek for T {}
 
@@ -63,6 +281,7 @@ impl<W: Write + Seek> MemDbBuilder<W> {
             } else {
                 None
             },
+            checksum: RefCell::new(digest::Context::new(&digest::SHA256)),
             info: info.clone(),
             symbols: vec![],
             symbols_map: HashMap::new(),
@@ -72,11 +291,12 @@ impl<W: Write + Seek> MemDbBuilder<W> {
             variant_uuids: vec![],
             variant_uuids_seen: HashSet::new(),
             variants: vec![],
+            variant_infos: vec![],
             symbol_count: 0,
             options: opts,
         };
         let header = MemDbHeader { ..Default::default() };
-        rv.write(&header)?;
+        rv.write_header(&header)?;
         Ok(rv)
     }
 
@@ -89,6 +309,7 @@ impl<W: Write + Seek> MemDbBuilder<W> {
     }
 
     fn write_bytes(&self, x: &[u8]) -> Result<usize> {
+        self.checksum.borrow_mut().update(x);
         self.with_file(|w| {
             w.write_all(x)?;
             Ok(x.len())
@@ -96,14 +317,17 @@ impl<W: Write + Seek> MemDbBuilder<W> {
     }
 
     fn write<T>(&self, x: &T) -> Result<usize> {
-        unsafe {
-            let bytes : *const u8 = mem::transmute(x);
-            let size = mem::size_of_val(x);
-            self.with_file(|w| {
-                w.write_all(slice::from_raw_parts(bytes, size))?;
-                Ok(size)
-            })
-        }
+        self.write_bytes(as_bytes(x))
+    }
+
+    /// Writes the header to the start of the file.  Unlike the other
+    /// writes this is not part of the checksum.
+    fn write_header(&self, header: &MemDbHeader) -> Result<()> {
+        self.seek(0)?;
+        self.with_file(|w| {
+            w.write_all(as_bytes(header))?;
+            Ok(())
+        })
     }
 
     fn seek(&self, new_pos: usize) -> Result<()> {
@@ -129,52 +353,79 @@ impl<W: Write + Seek> MemDbBuilder<W> {
         symbol_count as u32
     }
 
-    fn add_object_name(&mut self, src: &str) -> u16 {
+    fn add_object_name(&mut self, src: &str) -> u32 {
         if let Some(&src_id) = self.object_names_map.get(src) {
-            return src_id as u16;
+            return src_id;
         }
         let object_count = self.object_names.len();
         self.object_names.push(src.to_string());
-        self.object_names_map.insert(src.to_string(), object_count as u16);
-        object_count as u16
+        self.object_names_map.insert(src.to_string(), object_count as u32);
+        object_count as u32
     }
 
-    pub fn write_object(&mut self, obj: &Object, filename: Option<&str>) -> Result<()> {
-        for variant in obj.variants() {
-            let src = variant.name().or(filename).unwrap();
-            if let Some(uuid) = variant.uuid() {
-                self.write_object_variant(&obj, &variant, &uuid, src)?;
-            }
-        }
-        Ok(())
+    fn write_extracted_variant(&mut self, var: &ExtractedVariant) -> bool {
+        self.add_object_uuid_mapping(&var.src, &var.arch, &var.uuid);
+        self.add_extracted_variant(var)
     }
 
-    fn write_object_variant(&mut self, obj: &Object, var: &Variant,
-                            uuid: &Uuid, src: &str) -> Result<bool> {
-        self.object_uuid_mapping.push((
-            format!("{}:{}", src, var.arch()),
-            *uuid
-        ));
+    fn add_extracted_variant(&mut self, var: &ExtractedVariant) -> bool {
+        let entries: Vec<_> = var.symbols.iter()
+            .map(|&(addr, size, kind, ref name)| (addr, size, kind, name.as_str()))
+            .collect();
+        self.add_variant(&var.uuid, &var.src, &var.arch, &entries[..],
+                         &var.unnamed_functions[..], var.vmaddr, var.vmsize)
+    }
+
+    pub fn write_sym_file(&mut self, sym_file: &SymFile) -> Result<bool> {
+        self.add_object_uuid_mapping(sym_file.name(), sym_file.arch(), sym_file.uuid());
+        let entries: Vec<_> = sym_file.symbols().iter()
+            .map(|&(addr, size, ref name)| (addr, size, SectionKind::Text, name.as_str()))
+            .collect();
+        Ok(self.add_variant(sym_file.uuid(), sym_file.name(), sym_file.arch(),
+                            &entries[..], &[], 0, 0))
+    }
+
+    /// Records that an object name and architecture refer to a UUID.
+    fn add_object_uuid_mapping(&mut self, src: &str, arch: &str, uuid: &Uuid) {
+        self.object_uuid_mapping.push((format!("{}:{}", src, arch), *uuid));
+    }
 
-        if self.variant_uuids_seen.contains(uuid) {
-            return Ok(false);
+    fn has_variant(&self, uuid: &Uuid) -> bool {
+        self.variant_uuids_seen.contains(uuid)
+    }
+
+    /// Adds the symbols of a variant to the index.
+    ///
+    /// Symbols are `(addr, size, kind, name)` tuples with addresses relative to
+    /// the image start and a size of 0 if unknown.  Functions without symbol
+    /// are added as boundaries.  If the size of the image is known an end
+    /// marker is written for it.  Returns `false` if the UUID was already
+    /// added.
+    fn add_variant(&mut self, uuid: &Uuid, src: &str, arch: &str,
+                   symbols: &[(u64, u64, SectionKind, &str)],
+                   unnamed_functions: &[(u64, u64)], vmaddr: u64, vmsize: u64)
+        -> bool
+    {
+        if self.has_variant(uuid) {
+            return false;
         }
         self.variant_uuids_seen.insert(*uuid);
-
-        let mut symbols = obj.symbols(var.arch())?;
         let src_id = self.add_object_name(src);
 
         // build symbol index
         let mut index = vec![];
-        for (addr, sym) in symbols.iter() {
+        for &(addr, size, kind, sym) in symbols.iter() {
             let sym_id = self.add_symbol(sym);
-            index.push(IndexItem::new(addr - var.vmaddr(), src_id, Some(sym_id)));
+            index.push(IndexItem::new(addr, size, src_id, Some(sym_id), kind));
             self.symbol_count += 1;
         }
+        for &(addr, size) in unnamed_functions.iter() {
+            index.push(IndexItem::new_unnamed_function(addr, size, src_id));
+        }
 
         // write an end marker if we know the image size
-        if var.vmsize() > 0 {
-            index.push(IndexItem::new(var.vmsize(), src_id, None));
+        if vmsize > 0 {
+            index.push(IndexItem::new(vmsize, 0, src_id, None, SectionKind::Other));
             self.symbol_count += 1;
         }
 
@@ -183,11 +434,12 @@ impl<W: Write + Seek> MemDbBuilder<W> {
         // register variant and uuid
         self.variant_uuids.push(IndexedUuid::new(uuid, self.variants.len()));
         self.variants.push(index);
+        self.variant_infos.push(VariantInfo::new(vmaddr, vmsize, src_id, arch));
 
-        Ok(true)
+        true
     }
 
-    fn make_string_slices(&self, strings: &[String], _try_compress: bool) -> Result<Vec<StoredSlice>> {
+    fn make_string_slices(&self, strings: &[String], try_compress: bool) -> Result<Vec<StoredSlice>> {
         let mut slices = vec![];
         let pb = ProgressBar::new(strings.len() as u64);
         pb.set_style(ProgressStyle::default_bar()
@@ -195,31 +447,104 @@ impl<W: Write + Seek> MemDbBuilder<W> {
         for string in strings.iter() {
             let offset = self.tell()?;
             pb.set_message(&string);
-            let len = self.write_bytes(string.as_bytes())?;
-            slices.push(StoredSlice::new(offset, len, false));
+            let compressed = if try_compress {
+                compress_string(string)?
+            } else {
+                None
+            };
+            let slice = if let Some(bytes) = compressed {
+                StoredSlice::new(offset, self.write_bytes(&bytes)?, true)
+            } else {
+                StoredSlice::new(offset, self.write_bytes(string.as_bytes())?, false)
+            };
+            slices.push(slice);
             pb.inc(1);
         }
         pb.finish_and_clear();
         Ok(slices)
     }
 
-    fn write_slices(&self, slices: &[StoredSlice], start: &mut u32, len: &mut u32) -> Result<()> {
-        *start = self.tell()? as u32;
+    /// Writes strings front-coded in groups and returns a slice per group.
+    ///
+    /// Every string is stored as the length of the prefix it shares with the
+    /// previous string, the length of the remainder and the remainder.  The
+    /// first string of a group shares nothing so groups decode on their own.
+    fn make_front_coded_groups(&self, strings: &[String]) -> Result<Vec<StoredSlice>> {
+        let mut groups = vec![];
+        let pb = ProgressBar::new(strings.len() as u64);
+        pb.set_style(ProgressStyle::default_bar()
+            .template("{wide_msg:.dim}\n{wide_bar} {pos:>5}/{len}"));
+        for chunk in strings.chunks(SYMBOL_RESTART_INTERVAL) {
+            let mut buf = vec![];
+            let mut prev: &[u8] = b"";
+            for string in chunk.iter() {
+                pb.set_message(&string);
+                let bytes = string.as_bytes();
+                let shared = prev.iter().zip(bytes.iter()).take_while(|&(a, b)| a == b).count();
+                write_uleb128(&mut buf, shared as u64);
+                write_uleb128(&mut buf, (bytes.len() - shared) as u64);
+                buf.extend_from_slice(&bytes[shared..]);
+                prev = bytes;
+                pb.inc(1);
+            }
+            let offset = self.tell()?;
+            groups.push(StoredSlice::new(offset, self.write_bytes(&buf)?, false));
+        }
+        pb.finish_and_clear();
+        Ok(groups)
+    }
+
+    /// Writes a table of slices and returns its start offset and length
+    fn write_slices(&self, slices: &[StoredSlice]) -> Result<(u64, u64)> {
+        let start = self.tell()? as u64;
         let pb = ProgressBar::new(slices.len() as u64);
         for item in slices.iter() {
             self.write(item)?;
             pb.inc(1);
         }
-        *len = slices.len() as u32;
         pb.finish_and_clear();
-        Ok(())
+        Ok((start, slices.len() as u64))
+    }
+
+    /// Brings symbols, object names and variants into a canonical order.
+    ///
+    /// Ids are otherwise assigned in the order objects are added.  Sorting
+    /// makes sure the same input always produces the same bytes.
+    fn sort_tables(&mut self) {
+        let symbol_ids = sort_strings(&mut self.symbols);
+        let object_name_ids = sort_strings(&mut self.object_names);
+        self.symbols_map.clear();
+        self.object_names_map.clear();
+
+        for variant in self.variants.iter_mut() {
+            for item in variant.iter_mut() {
+                *item = item.with_ids(object_name_ids[item.src_id() as usize],
+                                      item.sym_id().map(|x| symbol_ids[x as usize]));
+            }
+        }
+
+        // variants are stored in the order of their UUIDs
+        self.variant_uuids.sort_by_key(|x| x.uuid);
+        let mut variants = mem::replace(&mut self.variants, vec![]);
+        let variant_infos = mem::replace(&mut self.variant_infos, vec![]);
+        for (idx, indexed_uuid) in self.variant_uuids.iter_mut().enumerate() {
+            let info = &variant_infos[indexed_uuid.idx()];
+            self.variants.push(mem::replace(&mut variants[indexed_uuid.idx()], vec![]));
+            self.variant_infos.push(
+                info.with_src_id(object_name_ids[info.src_id() as usize]));
+            indexed_uuid.idx = idx as u32;
+        }
+
+        self.object_uuid_mapping.sort();
+        self.object_uuid_mapping.dedup();
     }
 
     pub fn flush(&mut self) -> Result<()> {
         println!("      Found {} symbols", style(self.symbol_count).cyan());
         let mut header = MemDbHeader { ..Default::default() };
-        header.version = 2;
+        header.version = MEMDB_VERSION;
         header.sdk_info.set_from_sdk_info(&self.info);
+        self.sort_tables();
 
         println!("{} Writing metadata", format_step(2, &self.options));
         // start by writing out the index of the variants and record the slices.
@@ -237,47 +562,112 @@ impl<W: Write + Seek> MemDbBuilder<W> {
             slices.push(StoredSlice::new(offset, (self.tell()? - offset), false));
         }
         pb.finish_and_clear();
-        self.write_slices(&slices[..], &mut header.variants_start,
-                          &mut header.variants_count)?;
+        let (start, count) = self.write_slices(&slices[..])?;
+        header.variants_start = start;
+        header.variants_count = count;
 
         // next write out the UUIDs.  Since these are fixed length we do not
         // need to use slices here.
-        header.uuids_start = self.tell()? as u32;
-        header.uuids_count = self.variant_uuids.len() as u32;
+        header.uuids_start = self.tell()? as u64;
+        header.uuids_count = self.variant_uuids.len() as u64;
         self.variant_uuids.sort_by_key(|x| x.uuid);
         for indexed_uuid in self.variant_uuids.iter() {
             self.write(indexed_uuid)?;
         }
 
-        // next we write out the name + arch -> uuid index mapping.  We also sort
-        // this by uuid so that the index matches up.
-        header.tagged_object_names_start = self.tell()? as u32;
+        // the variant metadata is in the same order as the variants
+        header.variant_info_start = self.tell()? as u64;
+        header.variant_info_count = self.variant_infos.len() as u64;
+        for variant_info in self.variant_infos.iter() {
+            self.write(variant_info)?;
+        }
+
+        // next we write out the name + arch strings.  We also sort this by
+        // uuid so that the index matches up for older readers.
+        let uuid_positions: HashMap<Uuid, usize> = self.variant_uuids.iter()
+            .enumerate()
+            .map(|(idx, x)| (x.uuid, idx))
+            .collect();
+        let mut object_name_index = vec![];
+        header.tagged_object_names_start = self.tell()? as u64;
         self.object_uuid_mapping.sort_by_key(|&(_, b)| b);
-        for &(ref tagged_object, _) in self.object_uuid_mapping.iter() {
+        for &(ref tagged_object, ref uuid) in self.object_uuid_mapping.iter() {
+            let offset = self.tell()?;
             self.write_bytes(format!("{}\x00", tagged_object).as_bytes())?;
+            object_name_index.push((tagged_object.as_str(), offset, uuid_positions[uuid]));
+        }
+        header.tagged_object_names_end = self.tell()? as u64;
+
+        // the name + arch -> uuid index is sorted by name so that lookups
+        // can binary search it.  If the same name shows up for multiple
+        // UUIDs the first one wins.
+        object_name_index.sort();
+        object_name_index.dedup_by_key(|x| x.0);
+        header.object_name_index_start = self.tell()? as u64;
+        header.object_name_index_count = object_name_index.len() as u64;
+        for &(tagged_object, offset, uuid_idx) in object_name_index.iter() {
+            self.write(&IndexedObjectName::new(
+                StoredSlice::new(offset, tagged_object.len(), false), uuid_idx))?;
         }
-        header.tagged_object_names_end = self.tell()? as u32;
 
         // now write out all the object name sources
         let slices = self.make_string_slices(&self.object_names[..], true)?;
-        self.write_slices(&slices[..], &mut header.object_names_start,
-                          &mut header.object_names_count)?;
+        let (start, count) = self.write_slices(&slices[..])?;
+        header.object_names_start = start;
+        header.object_names_count = count;
 
         println!("{} Writing symbol strings", format_step(3, &self.options));
 
-        // now write out all the symbols
-        let slices = self.make_string_slices(&self.symbols[..], true)?;
-        println!("{} Writing symbol index", format_step(4, &self.options));
-        self.write_slices(&slices[..], &mut header.symbols_start,
-                          &mut header.symbols_count)?;
+        // now write out all the symbols.  They are sorted at this point so
+        // neighbours tend to share long prefixes.
+        if self.options.front_code_symbols {
+            let groups = self.make_front_coded_groups(&self.symbols[..])?;
+            println!("{} Writing symbol index", format_step(4, &self.options));
+            let (start, count) = self.write_slices(&groups[..])?;
+            header.symbol_groups_start = start;
+            header.symbol_groups_count = count;
+            header.symbol_restart_interval = SYMBOL_RESTART_INTERVAL as u64;
+            header.symbols_count = self.symbols.len() as u64;
+        } else {
+            let slices = self.make_string_slices(&self.symbols[..], true)?;
+            println!("{} Writing symbol index", format_step(4, &self.options));
+            let (start, count) = self.write_slices(&slices[..])?;
+            header.symbols_start = start;
+            header.symbols_count = count;
+        }
+
+        // the symbol name index points from every named item back into the
+        // variant indexes.  It is sorted by symbol name so that reverse
+        // lookups can binary search it.
+        let mut symbol_name_index = vec![];
+        for (uuid_idx, indexed_uuid) in self.variant_uuids.iter().enumerate() {
+            for (item_idx, item) in self.variants[indexed_uuid.idx()].iter().enumerate() {
+                if let Some(sym_id) = item.sym_id() {
+                    symbol_name_index.push((sym_id, uuid_idx, item_idx));
+                }
+            }
+        }
+        {
+            let symbols = &self.symbols;
+            symbol_name_index.sort_by(|a, b| {
+                (&symbols[a.0 as usize], a.1, a.2).cmp(&(&symbols[b.0 as usize], b.1, b.2))
+            });
+        }
+        header.symbol_name_index_start = self.tell()? as u64;
+        header.symbol_name_index_count = symbol_name_index.len() as u64;
+        for &(sym_id, uuid_idx, item_idx) in symbol_name_index.iter() {
+            self.write(&IndexedSymbolName::new(sym_id, uuid_idx, item_idx))?;
+        }
 
         println!("{} Writing headers", format_step(5, &self.options));
 
         let file_size = self.tell()?;
 
         // write the updated header
-        self.seek(0)?;
-        self.write(&header)?;
+        let checksum = mem::replace(&mut *self.checksum.borrow_mut(),
+                                    digest::Context::new(&digest::SHA256)).finish();
+        header.checksum.copy_from_slice(checksum.as_ref());
+        self.write_header(&header)?;
 
         println!("      Indexed {} variants",
                  style(self.variant_uuids.len()).cyan());
@@ -314,19 +704,201 @@ pub fn dump_memdb<W: Write + Seek>(writer: W, info: &SdkInfo,
                                    opts: DumpOptions, objects: Objects)
     -> Result<()>
 {
+    dump_objects(writer, info, opts, objects, None)
+}
+
+/// Dumps objects into a writer reusing the variants of a previous memdb
+///
+/// Variants whose UUID is already in the base are not parsed again.
+/// Instead they are copied over from the base which needs to be for the
+/// same SDK.  Files with only known UUIDs are not parsed at all.
+pub fn dump_memdb_incremental<W: Write + Seek>(writer: W, info: &SdkInfo,
+                                               opts: DumpOptions, objects: Objects,
+                                               base: &MemDb)
+    -> Result<()>
+{
+    if base.info() != info {
+        return Err(Error::from("Base memdb is for a different SDK"));
+    }
+    dump_objects(writer, info, opts, objects, Some(base))
+}
+
+fn dump_objects<W: Write + Seek>(writer: W, info: &SdkInfo, opts: DumpOptions,
+                                 objects: Objects, base: Option<&MemDb>)
+    -> Result<()>
+{
+    // variants of the base are reused by UUID.  The names are only needed
+    // for files that do not store variant metadata.
+    let mut base_variants = HashMap::new();
+    if let Some(base) = base {
+        for uuid in base.list_uuids()? {
+            base_variants.insert(*uuid, ("", "unknown"));
+        }
+        for (name, arch, uuid) in base.list_object_names()? {
+            base_variants.insert(*uuid, (name, arch));
+        }
+    }
+
     println!("{} Processing {} files", format_step(1, &opts),
              style(objects.file_count()).cyan());
     let mut builder = MemDbBuilder::new(writer, info, opts)?;
     let pb = ProgressBar::new(objects.file_count() as u64);
     pb.set_style(ProgressStyle::default_bar()
         .template("{wide_msg:.dim}\n{wide_bar} {pos:>5}/{len}"));
-    for obj_res in objects {
-        let (offset, filename, obj) = obj_res?;
-        pb.set_message(&filename);
-        builder.write_object(&obj, Some(&filename))?;
-        pb.inc(offset as u64);
+
+    // objects are parsed in parallel but added in their original order so
+    // that the output does not depend on the scheduling.
+    let (job_tx, results) = spawn_workers(num_cpus::get(), &builder.options.sections);
+    let mut job_tx = Some(job_tx);
+    let mut objects = objects;
+    let mut pending = BTreeMap::new();
+    let mut reused_variants = HashMap::new();
+    let mut seq = 0;
+    let mut next_seq = 0;
+    while job_tx.is_some() || next_seq < seq {
+        // hand out objects while there are some left.  Once all are handed
+        // out the sender is dropped and we wait for the remaining results.
+        let result = if let Some(tx) = job_tx.take() {
+            if let Some(source_rv) = objects.next_source() {
+                let (offset, filename, source) = source_rv?;
+                let uuids = if base_variants.is_empty() { vec![] } else { source.uuids()? };
+                let mut reused = vec![];
+                let mut known = vec![];
+                if let Some(base) = base {
+                    for uuid in uuids.iter() {
+                        if let Some(&(name, arch)) = base_variants.get(uuid) {
+                            reused.push(extract_memdb_variant(base, name, arch, uuid)?);
+                            known.push(*uuid);
+                        }
+                    }
+                }
+                // only parse objects with variants that are not in the base
+                if !uuids.is_empty() && known.len() == uuids.len() {
+                    pending.insert(seq, (offset, filename, Ok(reused)));
+                } else {
+                    reused_variants.insert(seq, reused);
+                    tx.send((seq, offset, filename, source, known)).map_err(|_| {
+                        Error::from("object worker failed")
+                    })?;
+                }
+                seq += 1;
+                job_tx = Some(tx);
+            }
+            results.try_recv().ok()
+        } else {
+            Some(results.recv().map_err(|_| Error::from("object worker failed"))?)
+        };
+
+        if let Some((result_seq, offset, filename, rv)) = result {
+            pending.insert(result_seq, (offset, filename, rv));
+        }
+        while let Some((offset, filename, rv)) = pending.remove(&next_seq) {
+            pb.set_message(&filename);
+            for var in reused_variants.remove(&next_seq).unwrap_or_default().iter() {
+                builder.write_extracted_variant(var);
+            }
+            for var in rv?.iter() {
+                builder.write_extracted_variant(var);
+            }
+            pb.inc(offset as u64);
+            next_seq += 1;
+        }
+    }
+    pb.finish_and_clear();
+    builder.flush()?;
+    Ok(())
+}
+
+/// Dumps Breakpad symbol files into a writer
+pub fn dump_breakpad_memdb<W: Write + Seek>(writer: W, info: &SdkInfo,
+                                            opts: DumpOptions, paths: &[PathBuf])
+    -> Result<()>
+{
+    dump_sym_files(writer, info, opts, paths, None)
+}
+
+/// Dumps Breakpad symbol files into a writer reusing a previous memdb
+///
+/// Modules whose UUID is already in the base memdb keep the symbols of
+/// the base.
+pub fn dump_breakpad_memdb_incremental<W: Write + Seek>(writer: W, info: &SdkInfo,
+                                                        opts: DumpOptions,
+                                                        paths: &[PathBuf], base: &MemDb)
+    -> Result<()>
+{
+    if base.info() != info {
+        return Err(Error::from("Base memdb is for a different SDK"));
+    }
+    dump_sym_files(writer, info, opts, paths, Some(base))
+}
+
+fn dump_sym_files<W: Write + Seek>(writer: W, info: &SdkInfo, opts: DumpOptions,
+                                   paths: &[PathBuf], base: Option<&MemDb>)
+    -> Result<()>
+{
+    let base_uuids: HashSet<Uuid> = match base {
+        Some(base) => base.list_uuids()?.into_iter().cloned().collect(),
+        None => HashSet::new(),
+    };
+
+    println!("{} Processing {} files", format_step(1, &opts),
+             style(paths.len()).cyan());
+    let mut builder = MemDbBuilder::new(writer, info, opts)?;
+    let pb = ProgressBar::new(paths.len() as u64);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{wide_msg:.dim}\n{wide_bar} {pos:>5}/{len}"));
+    for path in paths.iter() {
+        pb.set_message(&path.display().to_string());
+        let sym_file = SymFile::from_path(path)?;
+        match base {
+            Some(base) if base_uuids.contains(sym_file.uuid()) => {
+                builder.write_extracted_variant(&extract_memdb_variant(
+                    base, sym_file.name(), sym_file.arch(), sym_file.uuid())?);
+            }
+            _ => {
+                builder.write_sym_file(&sym_file)?;
+            }
+        }
+        pb.inc(1);
     }
     pb.finish_and_clear();
     builder.flush()?;
     Ok(())
 }
+
+/// Merges several memdb files for the same SDK into one.
+///
+/// Variants are de-duplicated by UUID.  If the same UUID shows up in more
+/// than one file the first file wins.
+pub fn merge_memdbs<W: Write + Seek>(writer: W, opts: DumpOptions, memdbs: &[MemDb])
+    -> Result<()>
+{
+    let info = match memdbs.first() {
+        Some(memdb) => memdb.info().clone(),
+        None => { return Err(Error::from("No memdb files to merge")); }
+    };
+    if memdbs.iter().any(|x| x.info() != &info) {
+        return Err(Error::from("Can only merge memdb files of the same SDK"));
+    }
+
+    println!("{} Merging {} files", format_step(1, &opts),
+             style(memdbs.len()).cyan());
+    let mut builder = MemDbBuilder::new(writer, &info, opts)?;
+    for memdb in memdbs.iter() {
+        let object_names = memdb.list_object_names()?;
+        for uuid in memdb.list_uuids()? {
+            if builder.has_variant(uuid) {
+                continue;
+            }
+            let (name, arch) = object_names.iter()
+                .find(|&&(_, _, x)| x == uuid)
+                .map_or(("", "unknown"), |&(name, arch, _)| (name, arch));
+            builder.add_extracted_variant(&extract_memdb_variant(memdb, name, arch, uuid)?);
+        }
+        for &(name, arch, uuid) in object_names.iter() {
+            builder.add_object_uuid_mapping(name, arch, uuid);
+        }
+    }
+    builder.flush()?;
+    Ok(())
+}
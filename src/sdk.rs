@@ -1,44 +1,75 @@
 //! Provides SDK Information
 use std::fs;
 use std::fmt;
-use std::io::{Read, Write, Seek};
+use std::io::{Read, Write, Seek, Cursor};
 use std::path::{Path, PathBuf};
+use std::vec;
 
 use zip;
 use walkdir;
+use uuid::Uuid;
 use regex::Regex;
 use mach_object::Error as MachError;
 
 use super::{Result, Error, ErrorKind};
-use super::dsym::Object;
-use super::memdb::write::dump_memdb;
+use super::dsym::{Object, DEFAULT_SECTION, read_uuids};
+use super::memdb::read::MemDb;
+use super::memdb::write::{dump_memdb, dump_memdb_incremental, dump_breakpad_memdb,
+                          dump_breakpad_memdb_incremental};
 
 
+/// The files of an SDK.  Both zip entries and directory entries are sorted
+/// by name so that the iteration order is the same on all machines.
 enum ObjectIterSource {
     Zip {
         archive: zip::ZipArchive<fs::File>,
-        idx: usize,
+        indexes: vec::IntoIter<usize>,
     },
     Dir {
         path: PathBuf,
-        dir_iter: walkdir::Iter,
+        paths: vec::IntoIter<PathBuf>,
     }
 }
 
+/// The raw data of an object in an SDK
+///
+/// Unlike `Object` this can be sent to other threads for parsing.
+pub enum ObjectSource {
+    Buf(Vec<u8>),
+    Path(PathBuf),
+}
+
 /// Influences how a SDK dump happens
 #[derive(Clone)]
 pub struct DumpOptions {
     pub compress: bool,
+    /// Front-codes the symbol strings instead of compressing them.
+    pub front_code_symbols: bool,
+    /// The sections symbols are indexed from as `SEGMENT,SECTION` or
+    /// `SEGMENT` for all sections of a segment.
+    pub sections: Vec<String>,
+    /// Converts the Breakpad `.sym` files of the SDK instead of its
+    /// Mach-O files.
+    pub breakpad: bool,
 }
 
 impl Default for DumpOptions {
     fn default() -> DumpOptions {
         DumpOptions {
             compress: false,
+            front_code_symbols: false,
+            sections: vec![DEFAULT_SECTION.to_string()],
+            breakpad: false,
         }
     }
 }
 
+/// The files an SDK is converted from
+enum DumpSource {
+    Objects(Objects),
+    Breakpad(Vec<PathBuf>),
+}
+
 fn get_sdk_name_from_folder(folder: &str) -> Option<&'static str> {
     match folder {
         "iOS DeviceSupport" => Some("iOS"),
@@ -239,15 +270,25 @@ impl ObjectIterSource {
         let md = fs::metadata(path.as_ref())?;
         if md.is_file() {
             let f = fs::File::open(path.as_ref())?;
-            let zip = zip::ZipArchive::new(f)?;
+            let mut zip = zip::ZipArchive::new(f)?;
+            let mut names = vec![];
+            for idx in 0..zip.len() {
+                names.push((zip.by_index(idx)?.name().to_string(), idx));
+            }
+            names.sort();
             Ok(ObjectIterSource::Zip {
                 archive: zip,
-                idx: 0,
+                indexes: names.into_iter().map(|x| x.1).collect::<Vec<_>>().into_iter(),
             })
         } else {
+            let mut paths = vec![];
+            for dent_res in walkdir::WalkDir::new(path.as_ref()) {
+                paths.push(dent_res?.path().to_path_buf());
+            }
+            paths.sort();
             Ok(ObjectIterSource::Dir {
                 path: path.as_ref().to_path_buf(),
-                dir_iter: walkdir::WalkDir::new(path.as_ref()).into_iter(),
+                paths: paths.into_iter(),
             })
         }
     }
@@ -277,69 +318,88 @@ fn strip_archive_file_prefix(path: &str) -> &str {
 }
 
 impl Objects {
+    /// Iterates over the objects in a zip file or folder
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Objects> {
+        Ok(Objects {
+            source: ObjectIterSource::from_path(path)?,
+        })
+    }
+
     /// Returns an estimated count of the files in the source
     pub fn file_count(&self) -> usize {
         match self.source {
             ObjectIterSource::Zip { ref archive, .. } => {
                 archive.len()
             }
-            ObjectIterSource::Dir { ref path, .. } => {
-                walkdir::WalkDir::new(path).into_iter().count()
+            ObjectIterSource::Dir { ref paths, .. } => {
+                paths.len()
             }
         }
     }
 }
 
-impl<'a> Iterator for Objects {
-    type Item = Result<(usize, String, Object<'static>)>;
-
-    fn next(&mut self) -> Option<Result<(usize, String, Object<'static>)>> {
-        let mut offset = 0;
+impl ObjectSource {
+    /// Reads the UUIDs of the object without parsing it.
+    pub fn uuids(&self) -> Result<Vec<Uuid>> {
+        match *self {
+            ObjectSource::Buf(ref buf) => read_uuids(Cursor::new(&buf[..])),
+            ObjectSource::Path(ref path) => read_uuids(fs::File::open(path)?),
+        }
+    }
 
-        macro_rules! try_return_obj {
-            ($expr:expr, $name:expr) => {
-                match $expr {
-                    Ok(rv) => {
-                        return Some(Ok((offset, format!("/{}", $name), rv)));
-                    }
-                    Err(err) => {
-                        if let &ErrorKind::MachO(ref mach_err) = err.kind() {
-                            if let &MachError::LoadError(_) = mach_err {
-                                continue;
-                            }
-                        }
-                        return Some(Err(err.into()));
+    /// Parses the object.
+    ///
+    /// Returns `None` if the source is not a Mach-O file.
+    pub fn parse(self) -> Result<Option<Object<'static>>> {
+        let rv = match self {
+            ObjectSource::Buf(buf) => Object::from_vec(buf),
+            ObjectSource::Path(path) => Object::from_path(path),
+        };
+        match rv {
+            Ok(obj) => Ok(Some(obj)),
+            Err(err) => {
+                if let &ErrorKind::MachO(ref mach_err) = err.kind() {
+                    if let &MachError::LoadError(_) = mach_err {
+                        return Ok(None);
                     }
                 }
+                Err(err)
             }
         }
+    }
+}
+
+impl Objects {
+    /// Returns the next object without parsing it.
+    ///
+    /// The offset is the number of files that were advanced.
+    pub fn next_source(&mut self) -> Option<Result<(usize, String, ObjectSource)>> {
+        let mut offset = 0;
 
         loop {
             offset += 1;
             match self.source {
-                ObjectIterSource::Zip { ref mut archive, ref mut idx } => {
-                    if *idx >= archive.len() {
-                        break;
-                    }
-                    let mut f = iter_try!(archive.by_index(*idx));
-                    *idx += 1;
+                ObjectIterSource::Zip { ref mut archive, ref mut indexes } => {
+                    let idx = match indexes.next() {
+                        Some(idx) => idx,
+                        None => { break; }
+                    };
+                    let mut f = iter_try!(archive.by_index(idx));
                     let mut buf : Vec<u8> = vec![];
                     if iter_try!(f.read_to_end(&mut buf)) > 0 {
-                        try_return_obj!(Object::from_vec(buf),
-                            strip_archive_file_prefix(f.name()));
+                        return Some(Ok((offset,
+                            format!("/{}", strip_archive_file_prefix(f.name())),
+                            ObjectSource::Buf(buf))));
                     }
                 }
-                ObjectIterSource::Dir { ref path, ref mut dir_iter } => {
-                    if let Some(dent_res) = dir_iter.next() {
-                        let dent = iter_try!(dent_res);
-                        let md = iter_try!(dent.metadata());
+                ObjectIterSource::Dir { ref path, ref mut paths } => {
+                    if let Some(file_path) = paths.next() {
+                        let md = iter_try!(fs::symlink_metadata(&file_path));
                         if md.is_file() && md.len() > 0 {
                             let prefix = path.join("Symbols");
-                            let rp = dent.path().strip_prefix(&prefix)
-                                .unwrap_or(dent.path());
-                            try_return_obj!(
-                                Object::from_path(dent.path()),
-                                rp.display());
+                            let name = format!("/{}", file_path.strip_prefix(&prefix)
+                                .unwrap_or(&file_path).display());
+                            return Some(Ok((offset, name, ObjectSource::Path(file_path))));
                         }
                     } else {
                         break;
@@ -351,6 +411,21 @@ impl<'a> Iterator for Objects {
     }
 }
 
+impl<'a> Iterator for Objects {
+    type Item = Result<(usize, String, Object<'static>)>;
+
+    fn next(&mut self) -> Option<Result<(usize, String, Object<'static>)>> {
+        let mut offset = 0;
+        loop {
+            let (source_offset, name, source) = iter_try!(try_opt!(self.next_source()));
+            offset += source_offset;
+            if let Some(obj) = iter_try!(source.parse()) {
+                return Some(Ok((offset, name, obj)));
+            }
+        }
+    }
+}
+
 impl Sdk {
     /// Constructs a processor from a file system path
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Sdk> {
@@ -371,16 +446,68 @@ impl Sdk {
 
     /// Returns an object iterator
     pub fn objects<'a>(&'a self) -> Result<Objects> {
-        Ok(Objects {
-            source: ObjectIterSource::from_path(&self.path)?,
-        })
+        Objects::from_path(&self.path)
+    }
+
+    /// Returns the Breakpad symbol files in the SDK folder
+    ///
+    /// These are only converted if `DumpOptions::breakpad` is set.
+    pub fn breakpad_sym_files(&self) -> Result<Vec<PathBuf>> {
+        let mut rv = vec![];
+        if !fs::metadata(&self.path)?.is_dir() {
+            return Ok(rv);
+        }
+        for dent_res in walkdir::WalkDir::new(&self.path) {
+            let dent = dent_res?;
+            if dent.file_type().is_file() &&
+               dent.path().extension().and_then(|x| x.to_str()) == Some("sym") {
+                rv.push(dent.path().to_path_buf());
+            }
+        }
+        rv.sort();
+        Ok(rv)
+    }
+
+    /// Selects the files to convert depending on the dump options.
+    fn dump_source(&self, opts: &DumpOptions) -> Result<DumpSource> {
+        if !opts.breakpad {
+            return Ok(DumpSource::Objects(self.objects()?));
+        }
+        let sym_files = self.breakpad_sym_files()?;
+        if sym_files.is_empty() {
+            return Err(Error::from("No Breakpad symbol files found in the SDK"));
+        }
+        Ok(DumpSource::Breakpad(sym_files))
     }
 
     /// Writes a memdb file for the SDK
     ///
     /// This can then be later read with the `MemDb` type.
     pub fn dump_memdb<W: Write + Seek>(&self, writer: W, opts: DumpOptions) -> Result<()> {
-        dump_memdb(writer, self.info(), opts, self.objects()?)?;
-        Ok(())
+        match self.dump_source(&opts)? {
+            DumpSource::Objects(objects) => {
+                dump_memdb(writer, self.info(), opts, objects)
+            }
+            DumpSource::Breakpad(sym_files) => {
+                dump_breakpad_memdb(writer, self.info(), opts, &sym_files[..])
+            }
+        }
+    }
+
+    /// Writes a memdb file for the SDK reusing a previous memdb
+    ///
+    /// Objects that are already in the base memdb are copied over instead
+    /// of being parsed again.
+    pub fn dump_memdb_incremental<W: Write + Seek>(&self, writer: W, opts: DumpOptions,
+                                                   base: &MemDb) -> Result<()> {
+        match self.dump_source(&opts)? {
+            DumpSource::Objects(objects) => {
+                dump_memdb_incremental(writer, self.info(), opts, objects, base)
+            }
+            DumpSource::Breakpad(sym_files) => {
+                dump_breakpad_memdb_incremental(writer, self.info(), opts,
+                                                &sym_files[..], base)
+            }
+        }
     }
 }
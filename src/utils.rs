@@ -299,7 +299,14 @@ pub fn get_systemd_fd() -> Result<Option<RawFd>> {
 }
 
 /// A quick binary search by key.
-pub fn binsearch_by_key<'a, T, B, F>(slice: &'a [T], item: B, mut f: F) -> Option<&'a T>
+pub fn binsearch_by_key<'a, T, B, F>(slice: &'a [T], item: B, f: F) -> Option<&'a T>
+    where B: Ord, F: FnMut(&T) -> B
+{
+    binsearch_pos_by_key(slice, item, f).map(|pos| &slice[pos])
+}
+
+/// Like `binsearch_by_key` but returns the position of the item.
+pub fn binsearch_pos_by_key<T, B, F>(slice: &[T], item: B, mut f: F) -> Option<usize>
     where B: Ord, F: FnMut(&T) -> B
 {
     let mut low = 0;
@@ -316,15 +323,58 @@ pub fn binsearch_by_key<'a, T, B, F>(slice: &'a [T], item: B, mut f: F) -> Optio
     }
 
     if low > 0 && low <= slice.len() {
-        Some(&slice[low - 1])
+        Some(low - 1)
     } else {
         None
     }
 }
 
+/// Reads an unsigned LEB128 number at an offset and advances the offset.
+pub fn read_uleb128(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut rv = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *try_opt!(data.get(*offset));
+        *offset += 1;
+        if shift < 64 {
+            rv |= ((byte & 0x7f) as u64) << shift;
+        }
+        if byte & 0x80 == 0 {
+            return Some(rv);
+        }
+        shift += 7;
+    }
+}
+
+/// Appends an unsigned LEB128 number to a buffer.
+pub fn write_uleb128(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
 #[test]
 fn test_binsearch() {
     let seq = [0u32, 2, 4, 6, 8, 10];
     let m = binsearch_by_key(&seq[..], 5, |&x| x);
     assert_eq!(*m.unwrap(), 4);
 }
+
+#[test]
+fn test_uleb128() {
+    let mut buf = vec![];
+    for &value in [0u64, 1, 127, 128, 624485, !0].iter() {
+        write_uleb128(&mut buf, value);
+    }
+    let mut offset = 0;
+    for &value in [0u64, 1, 127, 128, 624485, !0].iter() {
+        assert_eq!(read_uleb128(&buf, &mut offset), Some(value));
+    }
+    assert_eq!(read_uleb128(&buf, &mut offset), None);
+}
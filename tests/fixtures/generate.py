@@ -0,0 +1,336 @@
+#!/usr/bin/env python
+"""Regenerates the binary test fixtures in this folder.
+
+    python tests/fixtures/generate.py
+
+memdb/v2.memdb
+    A version 2 memdb of ``tests/res`` (iOS 10.2 (14C92)) as the version 2
+    writer laid it out: a packed 82 byte header, the per variant index of
+    12 byte items, the variant slices, the uuid index, the tagged object
+    names, the object names and the symbols followed by their slices.
+    Only symbols in ``__TEXT,__text`` are indexed and addresses are
+    relative to ``__TEXT``.
+
+macho/libFixture.dylib
+    A small arm64 dylib with __TEXT at 0x180000000 and these functions
+    in ``__TEXT,__text`` (relative to ``__TEXT``):
+
+    0x1000  _fixture_named         symbol table and export trie
+    0x1020  _fixture_exported      export trie only
+    0x1040  -[FixtureClass run]    objc method list
+    0x1060  +[FixtureClass shared] objc small method list of the metaclass
+    0x1080  (no name)              function starts only
+"""
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+RES = os.path.join(HERE, '..', 'res')
+
+FAT_MAGIC = 0xcafebabe
+MH_MAGIC = 0xfeedface
+MH_MAGIC_64 = 0xfeedfacf
+LC_SEGMENT = 0x1
+LC_SYMTAB = 0x2
+LC_UUID = 0x1b
+LC_SEGMENT_64 = 0x19
+LC_ID_DYLIB = 0xd
+LC_DYLD_INFO_ONLY = 0x80000022
+LC_FUNCTION_STARTS = 0x26
+CPU_TYPE_ARM64 = 0x100000c
+MH_DYLIB = 6
+N_STAB = 0xe0
+N_TYPE = 0x0e
+N_SECT = 0x0e
+
+ARCHS = {
+    (7, 3): 'i386',
+    (0x1000007, 3): 'x86_64',
+    (12, 9): 'armv7',
+    (12, 11): 'armv7s',
+    (0x100000c, 0): 'arm64',
+}
+
+
+def cstr(data):
+    return data.split(b'\0', 1)[0].decode('utf-8')
+
+
+def thin_images(data):
+    """Yields the thin mach-o images of a (possibly fat) file."""
+    if struct.unpack('>I', data[:4])[0] == FAT_MAGIC:
+        count = struct.unpack('>I', data[4:8])[0]
+        for idx in range(count):
+            offset, size = struct.unpack('>II', data[16 + idx * 20:24 + idx * 20])
+            yield data[offset:offset + size]
+    else:
+        yield data
+
+
+def parse_image(data):
+    """Returns the parts of a thin image the version 2 writer used."""
+    magic, cputype, cpusubtype, _, ncmds = struct.unpack('<5I', data[:20])
+    is_64 = magic == MH_MAGIC_64
+    offset = 32 if is_64 else 28
+    image = {
+        'arch': ARCHS[(cputype, cpusubtype & 0xffffff)],
+        'sections': [],
+        'symbols': [],
+    }
+    symtab = None
+    for _ in range(ncmds):
+        cmd, cmdsize = struct.unpack('<II', data[offset:offset + 8])
+        lc = data[offset:offset + cmdsize]
+        if cmd in (LC_SEGMENT, LC_SEGMENT_64):
+            if is_64:
+                vmaddr, vmsize = struct.unpack('<QQ', lc[24:40])
+                nsects = struct.unpack('<I', lc[64:68])[0]
+                sects, sectsize = lc[72:], 80
+            else:
+                vmaddr, vmsize = struct.unpack('<II', lc[24:32])
+                nsects = struct.unpack('<I', lc[48:52])[0]
+                sects, sectsize = lc[56:], 68
+            if cstr(lc[8:24]) == '__TEXT':
+                image['vmaddr'], image['vmsize'] = vmaddr, vmsize
+            for idx in range(nsects):
+                sect = sects[idx * sectsize:(idx + 1) * sectsize]
+                image['sections'].append((cstr(sect[16:32]), cstr(sect[:16])))
+        elif cmd == LC_UUID:
+            image['uuid'] = lc[8:24]
+        elif cmd == LC_ID_DYLIB:
+            image['name'] = cstr(lc[struct.unpack('<I', lc[8:12])[0]:])
+        elif cmd == LC_SYMTAB:
+            symtab = struct.unpack('<4I', lc[8:24])
+        offset += cmdsize
+
+    symoff, nsyms, stroff, _ = symtab
+    entsize = 16 if is_64 else 12
+    for idx in range(nsyms):
+        entry = data[symoff + idx * entsize:symoff + (idx + 1) * entsize]
+        strx, n_type, n_sect = struct.unpack('<IBB', entry[:6])
+        value = struct.unpack('<Q' if is_64 else '<I', entry[8:])[0]
+        if n_type & N_STAB or n_type & N_TYPE != N_SECT or n_sect == 0:
+            continue
+        if image['sections'][n_sect - 1] != ('__TEXT', '__text'):
+            continue
+        image['symbols'].append((value, cstr(data[stroff + strx:])))
+    return image
+
+
+def write_memdb_v2(path, sdk, images):
+    name, (major, minor, patch), build = sdk
+    symbols, symbol_ids = [], {}
+    object_names, object_ids = [], {}
+    tagged, uuids, variants = [], [], []
+
+    def intern(strings, ids, s):
+        if s not in ids:
+            ids[s] = len(strings)
+            strings.append(s)
+        return ids[s]
+
+    for image in images:
+        tagged.append(('%s:%s' % (image['name'], image['arch']), image['uuid']))
+        src_id = intern(object_names, object_ids, image['name'])
+        index = []
+        for addr, sym in image['symbols']:
+            index.append((addr - image['vmaddr'], src_id,
+                          intern(symbols, symbol_ids, sym)))
+        index.append((image['vmsize'], src_id, 0xffffffff))
+        index.sort(key=lambda x: x[0])
+        uuids.append((image['uuid'], len(variants)))
+        variants.append(index)
+
+    out = bytearray(82)
+
+    def slices(items):
+        start = len(out)
+        for offset, length in items:
+            out.extend(struct.pack('<II', offset, length))
+        return start, len(items)
+
+    def strings(items):
+        rv = []
+        for s in items:
+            rv.append((len(out), len(s.encode('utf-8'))))
+            out.extend(s.encode('utf-8'))
+        return slices(rv)
+
+    variant_slices = []
+    for index in variants:
+        offset = len(out)
+        for addr, src_id, sym_id in index:
+            out.extend(struct.pack('<IHHI', addr & 0xffffffff,
+                                   (addr >> 32) & 0xffff, src_id, sym_id))
+        variant_slices.append((offset, len(out) - offset))
+    variants_start, variants_count = slices(variant_slices)
+
+    uuids_start = len(out)
+    for uuid, idx in sorted(uuids, key=lambda x: x[0]):
+        out.extend(uuid + struct.pack('<H', idx))
+
+    tagged_start = len(out)
+    for tag, _ in sorted(tagged, key=lambda x: x[1]):
+        out.extend(tag.encode('utf-8') + b'\0')
+    tagged_end = len(out)
+
+    object_names_start, object_names_count = strings(object_names)
+    symbols_start, symbols_count = strings(symbols)
+
+    out[:82] = struct.pack(
+        '<I8sHHH24s10I', 2, name.encode('utf-8'), major, minor, patch,
+        build.encode('utf-8'), variants_start, variants_count, uuids_start,
+        len(uuids), tagged_start, tagged_end, object_names_start,
+        object_names_count, symbols_start, symbols_count)
+
+    with open(path, 'wb') as f:
+        f.write(out)
+
+
+def uleb128(value):
+    rv = bytearray()
+    while True:
+        byte = value & 0x7f
+        value >>= 7
+        if value:
+            rv.append(byte | 0x80)
+        else:
+            rv.append(byte)
+            return bytes(rv)
+
+
+def pad(data, align=8):
+    return data + b'\0' * (-len(data) % align)
+
+
+def export_trie(exports):
+    """Builds a trie with one edge per export below a shared prefix."""
+    prefix = b'_fixture_'
+    leaves = []
+    for name, addr in exports:
+        info = uleb128(0) + uleb128(addr)
+        leaves.append((name[len(prefix):], uleb128(len(info)) + info + b'\0'))
+
+    # node offsets are ulebs so this converges after a few rounds
+    offsets = [0] * (len(leaves) + 2)
+    while True:
+        root = b'\0\1' + prefix + b'\0' + uleb128(offsets[1])
+        inner = bytearray(b'\0' + bytes([len(leaves)]))
+        for idx, (edge, _) in enumerate(leaves):
+            inner += edge + b'\0' + uleb128(offsets[idx + 2])
+        nodes = [root, bytes(inner)] + [leaf for _, leaf in leaves]
+        new_offsets = [0]
+        for node in nodes[:-1]:
+            new_offsets.append(new_offsets[-1] + len(node))
+        if new_offsets == offsets:
+            return pad(b''.join(nodes))
+        offsets = new_offsets
+
+
+def section(sectname, segname, addr, size, offset, align=3, flags=0):
+    return struct.pack('<16s16sQQIIIIIIII', sectname, segname, addr, size,
+                       offset, align, 0, 0, flags, 0, 0, 0)
+
+
+def segment(segname, vmaddr, vmsize, fileoff, filesize, prot, sections):
+    return struct.pack('<II16sQQQQiiII', LC_SEGMENT_64, 72 + 80 * len(sections),
+                       segname, vmaddr, vmsize, fileoff, filesize, prot, prot,
+                       len(sections), 0) + b''.join(sections)
+
+
+def write_fixture_dylib(path):
+    text = 0x180000000
+    data = text + 0x4000
+    linkedit = text + 0x5000
+    body = bytearray(0x5000)
+
+    def put(addr, fmt, *values):
+        offset = addr - text
+        packed = struct.pack('<' + fmt, *values)
+        body[offset:offset + len(packed)] = packed
+
+    # __TEXT: every function is a single ret
+    for addr in range(0x1000, 0x10a0, 0x20):
+        put(text + addr, 'I', 0xd65f03c0)
+    put(text + 0x2000, '4s7s', b'run', b'shared')
+    put(text + 0x2040, '13s', b'FixtureClass')
+    put(text + 0x2060, '8s', b'v16@0:8')
+
+    # __DATA: one class whose instance methods are a regular method list
+    # and whose class methods are a small (relative) method list
+    run, shared = text + 0x2000, text + 0x2004
+    class_name, types = text + 0x2040, text + 0x2060
+    selrefs = data + 0x10
+    cls, meta = data + 0x200, data + 0x228
+    ro, meta_ro = data + 0x100, data + 0x148
+    methods, meta_methods = data + 0x190, data + 0x1b0
+    put(data, 'Q', cls)
+    put(selrefs, 'QQ', run, shared)
+    put(ro, 'IIII7Q', 0, 8, 8, 0, 0, class_name, methods, 0, 0, 0, 0)
+    put(meta_ro, 'IIII7Q', 1, 40, 40, 0, 0, class_name, meta_methods, 0, 0, 0, 0)
+    put(methods, 'IIQQQ', 24, 1, run, types, text + 0x1040)
+    entry = meta_methods + 8
+    put(meta_methods, 'IIiii', 0x80000000 | 12, 1, selrefs + 8 - entry,
+        types - (entry + 4), text + 0x1060 - (entry + 8))
+    put(cls, '5Q', meta, 0, 0, 0, ro)
+    put(meta, '5Q', 0, 0, 0, 0, meta_ro)
+
+    # __LINKEDIT
+    starts = b''.join(uleb128(x) for x in [0x1000, 0x20, 0x20, 0x20, 0x20, 0])
+    starts = pad(starts)
+    trie = export_trie([(b'_fixture_named', 0x1000), (b'_fixture_exported', 0x1020)])
+    strtab = pad(b'\0_fixture_named\0')
+    symtab = struct.pack('<IBBHQ', 1, 0x0f, 1, 0, text + 0x1000)
+    starts_off = 0x5000
+    trie_off = starts_off + len(starts)
+    symoff = trie_off + len(trie)
+    stroff = symoff + len(symtab)
+    body += starts + trie + symtab + strtab
+
+    name = pad(b'/usr/lib/libFixture.dylib\0')
+    commands = [
+        segment(b'__TEXT', text, 0x4000, 0, 0x4000, 5, [
+            section(b'__text', b'__TEXT', text + 0x1000, 0xa0, 0x1000, 2, 0x80000400),
+            section(b'__objc_methname', b'__TEXT', text + 0x2000, 11, 0x2000, 0, 2),
+            section(b'__objc_classname', b'__TEXT', text + 0x2040, 13, 0x2040, 0, 2),
+            section(b'__objc_methtype', b'__TEXT', text + 0x2060, 8, 0x2060, 0, 2),
+        ]),
+        segment(b'__DATA', data, 0x1000, 0x4000, 0x1000, 3, [
+            section(b'__objc_classlist', b'__DATA', data, 8, 0x4000),
+            section(b'__objc_selrefs', b'__DATA', selrefs, 16, 0x4010),
+            section(b'__objc_const', b'__DATA', ro, 0xc4, 0x4100),
+            section(b'__objc_data', b'__DATA', cls, 0x50, 0x4200),
+        ]),
+        segment(b'__LINKEDIT', linkedit, 0x1000, 0x5000, len(body) - 0x5000, 1, []),
+        struct.pack('<IIIIII', LC_ID_DYLIB, 24 + len(name), 24, 2,
+                    0x10000, 0x10000) + name,
+        struct.pack('<II16s', LC_UUID, 24,
+                    bytes.fromhex('5c63a7e51d463c4d9a2e7f0a3b1c2d3e')),
+        struct.pack('<II10I', LC_DYLD_INFO_ONLY, 48, 0, 0, 0, 0, 0, 0, 0, 0,
+                    trie_off, len(trie)),
+        struct.pack('<II4I', LC_SYMTAB, 24, symoff, 1, stroff, len(strtab)),
+        struct.pack('<IIII', LC_FUNCTION_STARTS, 16, starts_off, len(starts)),
+    ]
+    cmds = b''.join(commands)
+    header = struct.pack('<8I', MH_MAGIC_64, CPU_TYPE_ARM64, 0, MH_DYLIB,
+                         len(commands), len(cmds), 0x100085, 0)
+    assert len(header) + len(cmds) <= 0x1000
+    body[:len(header) + len(cmds)] = header + cmds
+
+    with open(path, 'wb') as f:
+        f.write(body)
+
+
+def main():
+    images = []
+    for filename in sorted(os.listdir(RES)):
+        with open(os.path.join(RES, filename), 'rb') as f:
+            data = f.read()
+        images.extend(parse_image(x) for x in thin_images(data))
+    write_memdb_v2(os.path.join(HERE, 'memdb', 'v2.memdb'),
+                   ('iOS', (10, 2, 0), '14C92'), images)
+    write_fixture_dylib(os.path.join(HERE, 'macho', 'libFixture.dylib'))
+
+
+if __name__ == '__main__':
+    main()
@@ -1,9 +1,10 @@
 extern crate libsymbolserver;
 extern crate uuid;
 
-use libsymbolserver::dsym::Object;
+use libsymbolserver::dsym::{Object, SectionKind};
 
 const DEMO_DYLIB: &'static [u8] = include_bytes!("res/libKoreanConverter.dylib");
+const FIXTURE_DYLIB: &'static [u8] = include_bytes!("fixtures/macho/libFixture.dylib");
 
 #[test]
 fn test_basics() {
@@ -48,3 +49,51 @@ fn test_symbols() {
         assert_eq!(count, 15);
     }
 }
+
+#[test]
+fn test_symbol_sections() {
+    let obj = Object::from_slice(DEMO_DYLIB).unwrap();
+    for var in obj.variants() {
+        let mut symbols = obj.symbols(var.arch()).unwrap();
+        let mut iter = symbols.iter_sections(&["__TEXT".to_string()]);
+        let mut count = 0;
+        while let Some((_, _, kind)) = iter.next_with_kind() {
+            assert!(kind != SectionKind::Data && kind != SectionKind::Other);
+            count += 1;
+        }
+        assert!(count >= 15);
+    }
+
+    assert_eq!(SectionKind::from_names("__TEXT", "__text"), SectionKind::Text);
+    assert_eq!(SectionKind::from_names("__TEXT", "__auth_stubs"), SectionKind::Stubs);
+    assert_eq!(SectionKind::from_names("__DATA_CONST", "__const"), SectionKind::Data);
+}
+
+#[test]
+fn test_exports() {
+    let obj = Object::from_slice(FIXTURE_DYLIB).unwrap();
+    let symbols = obj.symbols("arm64").unwrap();
+    let mut exports = symbols.exports(&["__TEXT,__text".to_string()]);
+    exports.sort_by_key(|x| x.0);
+    assert_eq!(exports, vec![
+        (0x1000, "_fixture_named".to_string(), SectionKind::Text),
+        (0x1020, "_fixture_exported".to_string(), SectionKind::Text),
+    ]);
+
+    // exports outside of the requested sections are dropped
+    assert!(symbols.exports(&["__DATA,__data".to_string()]).is_empty());
+}
+
+#[test]
+fn test_objc_methods() {
+    let obj = Object::from_slice(FIXTURE_DYLIB).unwrap();
+    let symbols = obj.symbols("arm64").unwrap();
+    let mut methods = symbols.objc_methods(&["__TEXT,__text".to_string()]);
+    methods.sort_by_key(|x| x.0);
+
+    // the class method comes from a relative method list on the metaclass
+    assert_eq!(methods, vec![
+        (0x1040, "-[FixtureClass run]".to_string(), SectionKind::Text),
+        (0x1060, "+[FixtureClass shared]".to_string(), SectionKind::Text),
+    ]);
+}
@@ -0,0 +1,710 @@
+extern crate libsymbolserver;
+extern crate uuid;
+extern crate regex;
+extern crate tempdir;
+extern crate serde_json;
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use uuid::Uuid;
+use regex::Regex;
+use tempdir::TempDir;
+use libsymbolserver::ErrorKind;
+use libsymbolserver::sdk::{Sdk, SdkInfo, Objects, DumpOptions};
+use libsymbolserver::memdb::read::{MemDb, SymbolPattern};
+use libsymbolserver::memdb::write::{dump_memdb, dump_memdb_incremental, dump_breakpad_memdb,
+                                    merge_memdbs};
+use libsymbolserver::memdb::diff::MemDbDiff;
+use libsymbolserver::memdb::dump::{RowFormat, SymbolRow, write_symbol_rows};
+use libsymbolserver::{breakpad, gsym};
+use libsymbolserver::dsym::SectionKind;
+
+const DEMO_DYLIB: &'static [u8] = include_bytes!("res/libKoreanConverter.dylib");
+const OTHER_NAME: &'static str =
+    "/System/Library/CoreServices/Encodings/libKoreanConvertez.dylib";
+const FIXTURE_DYLIB: &'static [u8] = include_bytes!("fixtures/macho/libFixture.dylib");
+const FIXTURE_UUID: &'static str = "5c63a7e5-1d46-3c4d-9a2e-7f0a3b1c2d3e";
+
+fn build_memdb() -> Vec<u8> {
+    build_memdb_at("tests/res")
+}
+
+fn build_memdb_at<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let objects = Objects::from_path(path).unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    dump_memdb(&mut cursor, &info, Default::default(), objects).unwrap();
+    cursor.into_inner()
+}
+
+fn build_memdb_from_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let tmp = TempDir::new("memdb").unwrap();
+    for &(filename, data) in files {
+        fs::File::create(tmp.path().join(filename)).unwrap().write_all(data).unwrap();
+    }
+    build_memdb_at(tmp.path())
+}
+
+/// Builds a memdb from Breakpad modules with a single `foo` function.
+fn build_breakpad_memdb(modules: &[(&str, &str)]) -> Vec<u8> {
+    let tmp = TempDir::new("memdb").unwrap();
+    let mut paths = vec![];
+    for &(arch, id) in modules {
+        let path = tmp.path().join(format!("{}.sym", arch));
+        write!(fs::File::create(&path).unwrap(),
+               "MODULE mac {} {} libKoreanConverter.dylib\nFUNC 1000 20 0 foo\n",
+               arch, id).unwrap();
+        paths.push(path);
+    }
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let mut cursor = Cursor::new(vec![]);
+    dump_breakpad_memdb(&mut cursor, &info, Default::default(), &paths[..]).unwrap();
+    cursor.into_inner()
+}
+
+/// Returns the demo dylib with other UUIDs and install name but the same
+/// symbols.
+fn other_dylib() -> Vec<u8> {
+    let mut buf = DEMO_DYLIB.to_vec();
+    let replacements = [
+        ("fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b", "fe6d76d4-8c3a-3a9a-9f63-f4a475501f2b"),
+        ("383fbe5b-e16e-362f-8937-ed303ab58e72", "383fbe5b-e16e-362f-8937-ed303ab58e73"),
+    ];
+    for &(from, to) in replacements.iter() {
+        let from: Uuid = from.parse().unwrap();
+        let to: Uuid = to.parse().unwrap();
+        replace_all(&mut buf, from.as_bytes(), to.as_bytes());
+    }
+    replace_all(&mut buf, b"libKoreanConverter.dylib", b"libKoreanConvertez.dylib");
+    buf
+}
+
+fn replace_all(buf: &mut [u8], needle: &[u8], replacement: &[u8]) {
+    let mut pos = 0;
+    while let Some(found) = buf[pos..].windows(needle.len()).position(|x| x == needle) {
+        pos += found;
+        buf[pos..pos + needle.len()].copy_from_slice(replacement);
+        pos += needle.len();
+    }
+}
+
+#[test]
+fn test_roundtrip() {
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    assert_eq!(memdb.info(), &SdkInfo::new("iOS", 10, 2, 0, Some("14C92")));
+
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let sym = memdb.lookup_by_uuid(&uuid, 23316).unwrap();
+    assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+    assert_eq!(sym.addr(), 23312);
+    assert_eq!(sym.offset(), 4);
+    assert_eq!(sym.object_uuid(), uuid);
+    assert_eq!(sym.object_name(),
+               "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib");
+    assert_eq!(sym.section_kind(), SectionKind::Text);
+
+    let sym = memdb.lookup_by_object_name(
+        "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib",
+        "armv7s", 744692588 - 744677376).unwrap();
+    assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+    assert_eq!(sym.object_uuid(),
+               "383fbe5b-e16e-362f-8937-ed303ab58e72".parse().unwrap());
+}
+
+#[test]
+fn test_truncated() {
+    let mut buf = build_memdb();
+    let len = buf.len();
+    buf.truncate(len - 10);
+    match MemDb::from_vec(buf) {
+        Ok(_) => panic!("truncated memdb was accepted"),
+        Err(err) => match *err.kind() {
+            ErrorKind::BadMemDb(_) => {}
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+}
+
+#[test]
+fn test_unterminated_object_name() {
+    let mut buf = build_memdb();
+    let needle = b"libKoreanConverter.dylib:arm64\x00";
+    let pos = buf.windows(needle.len()).position(|x| x == &needle[..]).unwrap();
+    buf[pos + needle.len() - 1] = b'x';
+    match MemDb::from_vec(buf) {
+        Ok(_) => panic!("unterminated object name was accepted"),
+        Err(err) => match *err.kind() {
+            ErrorKind::BadMemDb(_) => {}
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+}
+
+#[test]
+fn test_checksum() {
+    let mut buf = build_memdb();
+    {
+        let memdb = MemDb::from_slice(&buf).unwrap();
+        assert_eq!(memdb.checksum().map(|x| x.len()), Some(32));
+        memdb.verify_checksum().unwrap();
+    }
+
+    // corrupt a symbol name in a way that validation cannot see
+    let needle = b"___CFFromMacKoreanLen";
+    let pos = buf.windows(needle.len()).position(|x| x == &needle[..]).unwrap();
+    buf[pos + 3] = b'X';
+    let memdb = MemDb::from_slice(&buf).unwrap();
+    assert!(memdb.verify_checksum().is_err());
+}
+
+#[test]
+fn test_read_v2() {
+    let memdb = MemDb::from_slice(include_bytes!("fixtures/memdb/v2.memdb")).unwrap();
+    assert_eq!(memdb.info(), &SdkInfo::new("iOS", 10, 2, 0, Some("14C92")));
+    assert!(memdb.checksum().is_none());
+
+    let arm64: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let armv7s: Uuid = "383fbe5b-e16e-362f-8937-ed303ab58e72".parse().unwrap();
+    assert_eq!(memdb.list_uuids().unwrap(), vec![&armv7s, &arm64]);
+
+    let name = "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib";
+    assert_eq!(memdb.find_uuid(name, "arm64").unwrap(), Some(&arm64));
+    assert_eq!(memdb.find_uuid(name, "armv7s").unwrap(), Some(&armv7s));
+    assert_eq!(memdb.find_uuid(name, "x86_64").unwrap(), None);
+
+    let sym = memdb.lookup_by_uuid(&arm64, 23316).unwrap();
+    assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+    assert_eq!(sym.addr(), 23312);
+    assert_eq!(sym.object_name(), name);
+    let sym = memdb.lookup_by_uuid(&armv7s, 0x3b6e).unwrap();
+    assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+    assert_eq!(sym.addr(), 0x3b6c);
+}
+
+#[test]
+fn test_reject_v3() {
+    let mut buf = build_memdb();
+    buf[0] = 3;
+    match MemDb::from_vec(buf) {
+        Ok(_) => panic!("version 3 memdb was accepted"),
+        Err(err) => match *err.kind() {
+            ErrorKind::UnsupportedMemDbVersion => {}
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+}
+
+#[test]
+fn test_compressed_path() {
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let objects = Objects::from_path("tests/res").unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    let options = DumpOptions { compress: true, ..Default::default() };
+    dump_memdb(&mut cursor, &info, options, objects).unwrap();
+
+    let tmp = TempDir::new("memdb").unwrap();
+    let path = tmp.path().join("iOS_10.2.0_14C92.memdbz");
+    fs::File::create(&path).unwrap().write_all(&cursor.into_inner()).unwrap();
+    let memdb = MemDb::from_compressed_path(&path).unwrap();
+    assert_eq!(memdb.info(), &info);
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 23316).unwrap().symbol(),
+               "___CFFromMacKoreanLen");
+}
+
+#[test]
+fn test_symbol_size() {
+    let memdb = MemDb::from_vec(build_memdb_at("tests/fixtures/macho")).unwrap();
+    let uuid: Uuid = FIXTURE_UUID.parse().unwrap();
+    let sizes: Vec<_> = memdb.iter_symbols(&uuid).unwrap()
+        .map(|x| x.unwrap())
+        .map(|x| (x.addr(), x.size(), x.symbol().to_string()))
+        .collect();
+    assert_eq!(sizes, vec![
+        (0x1000, Some(0x20), "_fixture_named".to_string()),
+        (0x1020, Some(0x20), "_fixture_exported".to_string()),
+        (0x1040, Some(0x20), "-[FixtureClass run]".to_string()),
+        (0x1060, Some(0x20), "+[FixtureClass shared]".to_string()),
+    ]);
+
+    // the first address past a symbol resolves to the next one
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x101f).unwrap().symbol(), "_fixture_named");
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x1020).unwrap().symbol(), "_fixture_exported");
+
+    // the last function of __text ends with the segment, nothing is past it
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x4bf4).unwrap().size(), Some(0xf1c));
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x5b10).unwrap().size(), Some(0x23000 - 0x5b10));
+    assert!(memdb.lookup_by_uuid(&uuid, 0x23000).is_none());
+}
+
+#[test]
+fn test_data_symbol_size() {
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let objects = Objects::from_path("tests/res").unwrap();
+    let options = DumpOptions { sections: vec!["__DATA".to_string()], ..Default::default() };
+    let mut cursor = Cursor::new(vec![]);
+    dump_memdb(&mut cursor, &info, options, objects).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+
+    // the last symbol of __DATA ends with the segment, past the end of __TEXT
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let sym = memdb.lookup_by_uuid(&uuid, 0x10cf600c).unwrap();
+    assert_eq!(sym.symbol(), "___CFStringEncodingDecomposeCharacter");
+    assert_eq!(sym.addr(), 0x10cf6008);
+    assert_eq!(sym.size(), Some(8));
+    assert_eq!(sym.section_kind(), SectionKind::Data);
+    assert!(memdb.lookup_by_uuid(&uuid, 0x10cf6010).is_none());
+}
+
+#[test]
+fn test_unnamed_functions() {
+    let memdb = MemDb::from_vec(build_memdb_at("tests/fixtures/macho")).unwrap();
+    let uuid: Uuid = FIXTURE_UUID.parse().unwrap();
+    assert_eq!(memdb.list_unnamed_functions(&uuid).unwrap(), vec![(0x1080, 0x2f80)]);
+
+    // lookups inside the function do not run into the preceding method
+    assert!(memdb.lookup_by_uuid(&uuid, 0x1084).is_none());
+    assert_eq!(memdb.lookup_unnamed_function(&uuid, 0x1084), Some((0x1080, Some(0x2f80))));
+    let sym = memdb.lookup_by_uuid(&uuid, 0x107c).unwrap();
+    assert_eq!(sym.symbol(), "+[FixtureClass shared]");
+    assert_eq!(sym.size(), Some(0x20));
+    assert!(memdb.lookup_unnamed_function(&uuid, 0x107c).is_none());
+
+    // the thumb bit of 32-bit ARM function starts does not make up functions
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let uuid: Uuid = "383fbe5b-e16e-362f-8937-ed303ab58e72".parse().unwrap();
+    assert!(memdb.list_unnamed_functions(&uuid).unwrap().is_empty());
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x3b6e).unwrap().symbol(),
+               "___CFFromMacKoreanLen");
+}
+
+#[test]
+fn test_object_info() {
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let info = memdb.object_info(&uuid).unwrap().unwrap();
+    assert_eq!(info.uuid(), uuid);
+    assert_eq!(info.arch(), "arm64");
+    assert_eq!(info.path(),
+               "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib");
+    assert!(info.vmsize() > 0);
+
+    let missing: Uuid = "00000000-0000-0000-0000-000000000000".parse().unwrap();
+    assert!(memdb.object_info(&missing).unwrap().is_none());
+}
+
+#[test]
+fn test_find_symbol() {
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let syms = memdb.find_symbol("___CFFromMacKoreanLen").unwrap();
+    let uuids: Vec<Uuid> = syms.iter().map(|x| x.object_uuid()).collect();
+    assert!(uuids.contains(&"fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap()));
+    assert!(uuids.contains(&"383fbe5b-e16e-362f-8937-ed303ab58e72".parse().unwrap()));
+    for sym in syms.iter() {
+        assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+        assert_eq!(sym.offset(), 0);
+    }
+    assert!(memdb.find_symbol("___DoesNotExist").unwrap().is_empty());
+}
+
+#[test]
+fn test_search_symbols() {
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let found: Vec<String> = memdb.search_symbols(SymbolPattern::Prefix("___CFFromMacKorean"))
+        .unwrap().map(|x| x.unwrap().into_owned()).collect();
+    assert_eq!(found, vec!["___CFFromMacKorean".to_string(),
+                           "___CFFromMacKoreanLen".to_string()]);
+
+    // the indexed prefix search agrees with a full scan
+    let regex = Regex::new("^___CFTo").unwrap();
+    let scanned: Vec<String> = memdb.search_symbols(SymbolPattern::Regex(&regex))
+        .unwrap().map(|x| x.unwrap().into_owned()).collect();
+    let found: Vec<String> = memdb.search_symbols(SymbolPattern::Prefix("___CFTo"))
+        .unwrap().map(|x| x.unwrap().into_owned()).collect();
+    assert_eq!(scanned.len(), 7);
+    assert_eq!(found, scanned);
+
+    assert_eq!(memdb.search_symbols(SymbolPattern::Prefix("___CFZ")).unwrap().count(), 0);
+    assert_eq!(memdb.search_symbols(SymbolPattern::Prefix("")).unwrap().count(),
+               memdb.search_symbols(SymbolPattern::Regex(&Regex::new("").unwrap()))
+                   .unwrap().count());
+}
+
+#[test]
+fn test_merge() {
+    let a = build_memdb();
+    let b = build_memdb_from_files(&[("other.dylib", &other_dylib()[..])]);
+    let memdbs = vec![MemDb::from_slice(&a).unwrap(), MemDb::from_slice(&b).unwrap()];
+    let mut cursor = Cursor::new(vec![]);
+    merge_memdbs(&mut cursor, Default::default(), &memdbs[..]).unwrap();
+    let merged = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert_eq!(merged.info(), memdbs[0].info());
+
+    let name = "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib";
+    let objects = [
+        (name, "arm64", "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b", 23316),
+        (name, "armv7s", "383fbe5b-e16e-362f-8937-ed303ab58e72", 0x3b6c),
+        (OTHER_NAME, "arm64", "fe6d76d4-8c3a-3a9a-9f63-f4a475501f2b", 23316),
+        (OTHER_NAME, "armv7s", "383fbe5b-e16e-362f-8937-ed303ab58e73", 0x3b6c),
+    ];
+    let mut uuids = vec![];
+    for &(name, arch, uuid, addr) in objects.iter() {
+        let uuid: Uuid = uuid.parse().unwrap();
+        assert_eq!(merged.find_uuid(name, arch).unwrap(), Some(&uuid));
+        let sym = merged.lookup_by_uuid(&uuid, addr).unwrap();
+        assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+        assert_eq!(sym.object_name(), name);
+        let source = if name == OTHER_NAME { &memdbs[1] } else { &memdbs[0] };
+        assert_eq!(sym.size(), source.lookup_by_uuid(&uuid, addr).unwrap().size());
+        uuids.push(uuid);
+    }
+    uuids.sort();
+    assert_eq!(merged.list_uuids().unwrap(), uuids.iter().collect::<Vec<_>>());
+
+    let mut found: Vec<_> = merged.find_symbol("___CFFromMacKoreanLen").unwrap()
+        .iter().map(|x| x.object_uuid()).collect();
+    found.sort();
+    assert_eq!(found, uuids);
+}
+
+#[test]
+fn test_diff() {
+    let name = "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib";
+    let arm64: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let new_arm64: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1c".parse().unwrap();
+    let mut changed_dylib = DEMO_DYLIB.to_vec();
+    replace_all(&mut changed_dylib, arm64.as_bytes(), new_arm64.as_bytes());
+
+    let old = MemDb::from_vec(build_memdb_from_files(&[("a.dylib", DEMO_DYLIB)])).unwrap();
+    let other = other_dylib();
+    let extended = MemDb::from_vec(build_memdb_from_files(&[
+        ("a.dylib", DEMO_DYLIB),
+        ("b.dylib", &other),
+    ])).unwrap();
+    let changed = MemDb::from_vec(build_memdb_from_files(&[
+        ("a.dylib", &changed_dylib),
+    ])).unwrap();
+
+    // added objects keep the SDK compatible
+    let diff = MemDbDiff::compare(&old, &extended).unwrap();
+    assert!(diff.is_compatible());
+    assert_eq!(diff.added.iter().map(|x| (&x.name[..], &x.arch[..])).collect::<Vec<_>>(),
+               vec![(OTHER_NAME, "arm64"), (OTHER_NAME, "armv7s")]);
+    assert!(diff.added.iter().all(|x| x.old_uuid.is_none() && x.old_symbol_count == 0));
+    assert!(diff.added.iter().all(|x| x.new_symbol_count > 0));
+    assert!(diff.removed.is_empty());
+    assert!(diff.changed.is_empty());
+    assert_eq!(diff.unchanged_count, 2);
+    assert_eq!(diff.new_symbol_count, diff.old_symbol_count * 2);
+
+    // removed objects do not
+    let diff = MemDbDiff::compare(&extended, &old).unwrap();
+    assert!(!diff.is_compatible());
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.removed.iter().map(|x| (&x.name[..], &x.arch[..])).collect::<Vec<_>>(),
+               vec![(OTHER_NAME, "arm64"), (OTHER_NAME, "armv7s")]);
+    assert!(diff.removed.iter().all(|x| x.new_uuid.is_none() && x.new_symbol_count == 0));
+
+    // neither do changed UUIDs
+    let diff = MemDbDiff::compare(&old, &changed).unwrap();
+    assert!(!diff.is_compatible());
+    assert!(diff.added.is_empty());
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].name, name);
+    assert_eq!(diff.changed[0].arch, "arm64");
+    assert_eq!(diff.changed[0].old_uuid, Some(arm64));
+    assert_eq!(diff.changed[0].new_uuid, Some(new_arm64));
+    assert_eq!(diff.changed[0].symbol_count_delta(), 0);
+    assert_eq!(diff.unchanged_count, 1);
+
+    let json: serde_json::Value = serde_json::from_str(
+        &serde_json::to_string(&diff).unwrap()).unwrap();
+    assert_eq!(json["added"].as_array().map(|x| x.len()), Some(0));
+    assert_eq!(json["removed"].as_array().map(|x| x.len()), Some(0));
+    assert_eq!(json["changed"][0]["name"].as_str(), Some(name));
+    assert_eq!(json["changed"][0]["arch"].as_str(), Some("arm64"));
+    assert_eq!(json["changed"][0]["old_uuid"].as_str(),
+               Some("fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b"));
+    assert_eq!(json["changed"][0]["new_uuid"].as_str(),
+               Some("fe6d76d4-8c3a-3a9a-9f63-f4a475501f1c"));
+    assert_eq!(json["unchanged_count"].as_u64(), Some(1));
+}
+
+#[test]
+fn test_breakpad_export() {
+    let memdb = MemDb::from_vec(build_memdb()).unwrap();
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let mut out = vec![];
+    breakpad::write_sym(&mut out, &memdb,
+        "/System/Library/CoreServices/Encodings/libKoreanConverter.dylib",
+        "arm64", &uuid).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let mut lines = out.lines();
+    assert_eq!(lines.next(),
+               Some("MODULE mac arm64 FE6D76D48C3A3A9A9F63F4A475501F1B0 libKoreanConverter.dylib"));
+    assert!(out.contains("\nPUBLIC 5b10 0 __CFFromMacKoreanLen\n"));
+}
+
+#[test]
+fn test_breakpad_import() {
+    let sym = "MODULE mac arm64 FE6D76D48C3A3A9A9F63F4A475501F1B0 libFoo.dylib\n\
+               FILE 0 foo.c\n\
+               FUNC 1000 20 0 foo\n\
+               1000 10 1 0\n\
+               PUBLIC 1100 0 bar\n\
+               PUBLIC 1200 0 baz\n";
+    let sym_file = breakpad::SymFile::parse(sym.as_bytes()).unwrap();
+    assert_eq!(sym_file.arch(), "arm64");
+    assert_eq!(sym_file.name(), "libFoo.dylib");
+    assert_eq!(sym_file.uuid(), &"fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap());
+    assert_eq!(sym_file.symbols(), &[
+        (0x1000, 0x20, "foo".to_string()),
+        (0x1100, 0x100, "bar".to_string()),
+        (0x1200, 0, "baz".to_string()),
+    ]);
+}
+
+#[test]
+fn test_breakpad_bad_module_id() {
+    for id in &["FE6D76D48C3A", "FE6D76D48C3A3A9A9F63F4A475501F1\u{e9}0"] {
+        let sym = format!("MODULE mac arm64 {} libFoo.dylib\nFUNC 1000 20 0 foo\n", id);
+        match breakpad::SymFile::parse(sym.as_bytes()) {
+            Ok(_) => panic!("invalid module id {} was accepted", id),
+            Err(err) => match *err.kind() {
+                ErrorKind::BadBreakpadSym(_) => {}
+                _ => panic!("unexpected error: {}", err),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sdk_breakpad_mode() {
+    let tmp = TempDir::new("sdk").unwrap();
+    let path = tmp.path().join("iOS_10.2.0_14C92");
+    fs::create_dir(&path).unwrap();
+    fs::File::create(path.join("libKoreanConverter.dylib")).unwrap()
+        .write_all(DEMO_DYLIB).unwrap();
+    fs::File::create(path.join("libFoo.sym")).unwrap()
+        .write_all(b"MODULE mac arm64 4C4C44A1D8E83E1C9C7C5B1D2F4C7E2A0 libFoo.dylib\n\
+                     FUNC 1000 20 0 foo\n").unwrap();
+    let sdk = Sdk::new(&path).unwrap();
+    let foo: Uuid = "4c4c44a1-d8e8-3e1c-9c7c-5b1d2f4c7e2a".parse().unwrap();
+    let korean: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+
+    // symbol files are ignored unless asked for
+    let mut cursor = Cursor::new(vec![]);
+    sdk.dump_memdb(&mut cursor, Default::default()).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert!(memdb.list_uuids().unwrap().contains(&&korean));
+    assert!(!memdb.list_uuids().unwrap().contains(&&foo));
+
+    let options = DumpOptions { breakpad: true, ..Default::default() };
+    let mut cursor = Cursor::new(vec![]);
+    sdk.dump_memdb(&mut cursor, options.clone()).unwrap();
+    let base = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert_eq!(base.list_uuids().unwrap(), vec![&foo]);
+
+    // incremental dumps convert the same files
+    let mut cursor = Cursor::new(vec![]);
+    sdk.dump_memdb_incremental(&mut cursor, options.clone(), &base).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert_eq!(memdb.list_uuids().unwrap(), vec![&foo]);
+    assert_eq!(memdb.lookup_by_uuid(&foo, 0x1010).unwrap().symbol(), "foo");
+
+    // asking for Breakpad without symbol files fails
+    fs::remove_file(path.join("libFoo.sym")).unwrap();
+    assert!(sdk.dump_memdb(&mut Cursor::new(vec![]), options).is_err());
+}
+
+#[test]
+fn test_gsym_export() {
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let mut out = vec![];
+    gsym::write_gsym(&mut out, &uuid, &[(0x1100, 0x10, "bar"), (0x1000, 0x20, "foo")]).unwrap();
+    assert_eq!(&out[..4], b"MYSG");
+    // two byte address offsets, 16 byte uuid and a base of 0x1000
+    assert_eq!(out[6], 2);
+    assert_eq!(out[7], 16);
+    assert_eq!(&out[8..16], &[0, 0x10, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&out[16..20], &[2, 0, 0, 0]);
+    assert_eq!(&out[28..44], uuid.as_bytes());
+    assert_eq!(&out[48..52], &[0, 0, 0, 1]);
+}
+
+#[test]
+fn test_front_coded() {
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let objects = Objects::from_path("tests/res").unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    let options = DumpOptions { front_code_symbols: true, ..Default::default() };
+    dump_memdb(&mut cursor, &info, options, objects).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+    let plain = MemDb::from_vec(build_memdb()).unwrap();
+
+    let symbols: Vec<_> = memdb.search_symbols(SymbolPattern::Prefix(""))
+        .unwrap().map(|x| x.unwrap().into_owned()).collect();
+    let plain_symbols: Vec<_> = plain.search_symbols(SymbolPattern::Prefix(""))
+        .unwrap().map(|x| x.unwrap().into_owned()).collect();
+    assert_eq!(symbols, plain_symbols);
+
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 23316).unwrap().symbol(),
+               "___CFFromMacKoreanLen");
+    assert_eq!(memdb.find_symbol("___CFFromMacKoreanLen").unwrap().len(),
+               plain.find_symbol("___CFFromMacKoreanLen").unwrap().len());
+}
+
+#[test]
+fn test_deterministic() {
+    assert!(build_memdb() == build_memdb());
+
+    // the same objects found in a different order give the same file
+    let other = other_dylib();
+    let a = build_memdb_from_files(&[("a.dylib", DEMO_DYLIB), ("b.dylib", &other[..])]);
+    let b = build_memdb_from_files(&[("a.dylib", &other[..]), ("b.dylib", DEMO_DYLIB)]);
+    assert_eq!(MemDb::from_slice(&a).unwrap().list_uuids().unwrap().len(), 4);
+    assert!(a == b);
+}
+
+#[test]
+fn test_incremental() {
+    let info = SdkInfo::new("iOS", 10, 2, 0, Some("14C92"));
+    let base = MemDb::from_vec(build_memdb()).unwrap();
+    let objects = Objects::from_path("tests/res").unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    dump_memdb_incremental(&mut cursor, &info, Default::default(), objects, &base).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+
+    assert_eq!(memdb.list_uuids().unwrap(), base.list_uuids().unwrap());
+    let uuid: Uuid = "fe6d76d4-8c3a-3a9a-9f63-f4a475501f1b".parse().unwrap();
+    let sym = memdb.lookup_by_uuid(&uuid, 23316).unwrap();
+    assert_eq!(sym.symbol(), "___CFFromMacKoreanLen");
+    assert_eq!(sym.size(), base.lookup_by_uuid(&uuid, 23316).unwrap().size());
+    assert_eq!(memdb.list_unnamed_functions(&uuid).unwrap(),
+               base.list_unnamed_functions(&uuid).unwrap());
+
+    // variants are reused by UUID so the symbols of the base win
+    let armv7s: Uuid = "383fbe5b-e16e-362f-8937-ed303ab58e72".parse().unwrap();
+    let base = MemDb::from_vec(build_breakpad_memdb(&[
+        ("arm64", "FE6D76D48C3A3A9A9F63F4A475501F1B0"),
+        ("armv7s", "383FBE5BE16E362F8937ED303AB58E720"),
+    ])).unwrap();
+    let objects = Objects::from_path("tests/res").unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    dump_memdb_incremental(&mut cursor, &info, Default::default(), objects, &base).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert_eq!(memdb.list_uuids().unwrap(), vec![&armv7s, &uuid]);
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x1004).unwrap().symbol(), "foo");
+    assert_eq!(memdb.lookup_by_uuid(&armv7s, 0x1004).unwrap().symbol(), "foo");
+    assert!(memdb.find_symbol("___CFFromMacKoreanLen").unwrap().is_empty());
+
+    // variants missing from the base are parsed
+    let base = MemDb::from_vec(build_breakpad_memdb(&[
+        ("arm64", "FE6D76D48C3A3A9A9F63F4A475501F1B0"),
+    ])).unwrap();
+    let objects = Objects::from_path("tests/res").unwrap();
+    let mut cursor = Cursor::new(vec![]);
+    dump_memdb_incremental(&mut cursor, &info, Default::default(), objects, &base).unwrap();
+    let memdb = MemDb::from_vec(cursor.into_inner()).unwrap();
+    assert_eq!(memdb.list_uuids().unwrap(), vec![&armv7s, &uuid]);
+    assert_eq!(memdb.lookup_by_uuid(&uuid, 0x1004).unwrap().symbol(), "foo");
+    assert_eq!(memdb.lookup_by_uuid(&armv7s, 0x3b6c).unwrap().symbol(),
+               "___CFFromMacKoreanLen");
+    assert_eq!(memdb.find_symbol("___CFFromMacKoreanLen").unwrap().len(), 1);
+}
+
+#[test]
+fn test_stats() {
+    let memdb = MemDb::from_vec(build_memdb_at("tests/fixtures/macho")).unwrap();
+    let uuid: Uuid = FIXTURE_UUID.parse().unwrap();
+    let stats = memdb.stats().unwrap();
+    assert_eq!(stats.version, 4);
+    assert_eq!(stats.variant_count, 1);
+    assert_eq!(stats.uuid_count, 1);
+    assert_eq!(stats.object_name_count, 1);
+    assert_eq!(stats.symbol_count, 4);
+    assert_eq!(stats.unnamed_function_count, 1);
+    assert_eq!(stats.objects, vec![
+        ("/usr/lib/libFixture.dylib".to_string(), "arm64".to_string(), uuid, 4),
+    ]);
+
+    // symbols are counted per variant
+    let memdb = MemDb::from_vec(build_memdb_from_files(&[
+        ("a.dylib", DEMO_DYLIB),
+        ("b.dylib", FIXTURE_DYLIB),
+    ])).unwrap();
+    let stats = memdb.stats().unwrap();
+    assert_eq!(stats.variant_count, 3);
+    assert_eq!(stats.unnamed_function_count, 1);
+    let counts: Vec<_> = stats.objects.iter().map(|x| (&x.1[..], x.3)).collect();
+    assert!(counts.contains(&("arm64", 4)));
+    assert_eq!(stats.objects.iter().map(|x| x.3).sum::<usize>(),
+               memdb.list_uuids().unwrap().iter()
+                   .map(|uuid| memdb.iter_symbols(uuid).unwrap().count())
+                   .sum::<usize>());
+}
+
+#[test]
+fn test_symbol_rows() {
+    let row = SymbolRow {
+        object_name: "/usr/lib/a,b.dylib",
+        arch: "arm64",
+        uuid: FIXTURE_UUID.to_string(),
+        addr: 0x1000,
+        size: Some(0x20),
+        symbol: "say \"hi\"",
+    };
+    let mut out = vec![];
+    row.write(&mut out, RowFormat::Csv).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "\"/usr/lib/a,b.dylib\",arm64,5c63a7e5-1d46-3c4d-9a2e-7f0a3b1c2d3e,\
+                4096,32,\"say \"\"hi\"\"\"\n");
+
+    // tabs do not need quoting, missing sizes stay empty
+    let row = SymbolRow { symbol: "a\tb", size: None, object_name: "a.dylib", ..row };
+    let mut out = vec![];
+    row.write(&mut out, RowFormat::Csv).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "a.dylib,arm64,5c63a7e5-1d46-3c4d-9a2e-7f0a3b1c2d3e,4096,,a\tb\n");
+
+    let mut out = vec![];
+    row.write(&mut out, RowFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(json["symbol"].as_str(), Some("a\tb"));
+    assert!(json["size"].is_null());
+    assert_eq!(json["addr"].as_u64(), Some(0x1000));
+}
+
+#[test]
+fn test_write_symbol_rows() {
+    let memdb = MemDb::from_vec(build_memdb_at("tests/fixtures/macho")).unwrap();
+    let mut out = vec![];
+    write_symbol_rows(&mut out, &memdb, RowFormat::Csv).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "object_name,arch,uuid,addr,size,symbol");
+    assert!(lines.contains(&"/usr/lib/libFixture.dylib,arm64,\
+                            5c63a7e5-1d46-3c4d-9a2e-7f0a3b1c2d3e,4160,32,-[FixtureClass run]"));
+
+    let mut out = vec![];
+    write_symbol_rows(&mut out, &memdb, RowFormat::Json).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 4);
+}
+
+#[test]
+fn test_objc_method_lookup() {
+    let memdb = MemDb::from_vec(build_memdb_at("tests/fixtures/macho")).unwrap();
+    let uuid: Uuid = FIXTURE_UUID.parse().unwrap();
+    let sym = memdb.lookup_by_uuid(&uuid, 0x1044).unwrap();
+    assert_eq!(sym.symbol(), "-[FixtureClass run]");
+    assert_eq!(sym.addr(), 0x1040);
+    let found = memdb.find_symbol("-[FixtureClass run]").unwrap();
+    assert_eq!(found.iter().map(|x| x.addr()).collect::<Vec<_>>(), vec![0x1040]);
+}